@@ -36,5 +36,84 @@ fn criterion_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, criterion_benchmark);
+fn code_loading_benchmark(c: &mut Criterion) {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_4.elf")).unwrap();
+    let config = SimulationConfig::default();
+
+    let mut group = c.benchmark_group("code_loading");
+    group.sample_size(10);
+    // Code is written to a new instance for every run
+    group.bench_function("1000 runs, new instance", |b| {
+        b.iter(|| {
+            for _ in 0..1000 {
+                let mut simulation = Control::new(&file_data, &config).unwrap();
+                simulation.start();
+                simulation.run_steps(DEFAULT_INSTRUCTION_LIMIT);
+            }
+        })
+    });
+    // Unmodified read-only code is kept between the runs
+    group.bench_function("1000 runs, reused instance", |b| {
+        let mut simulation = Control::new(&file_data, &config).unwrap();
+        b.iter(|| {
+            for _ in 0..1000 {
+                simulation.start();
+                simulation.run_steps(DEFAULT_INSTRUCTION_LIMIT);
+            }
+        })
+    });
+}
+
+fn attack_path_benchmark(c: &mut Criterion) {
+    let mut attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    // Add a synthetic read-only segment of 256KB behind the code to get a large image
+    let (mut header, _) = attack.file_data.program_data[0].clone();
+    header.p_paddr = 0x0804_0000;
+    header.p_vaddr = 0x0804_0000;
+    header.p_filesz = 0x40000;
+    header.p_memsz = 0x40000;
+    attack
+        .file_data
+        .program_data
+        .push((header, vec![0; 0x40000]));
+
+    // 1000 single glitch attacks spread over the candidates of the nominal run
+    let glitch = get_fault_from("glitch").unwrap();
+    let candidates = attack.enumerate_fault_candidates(2000, &glitch).unwrap();
+    let attacks: Vec<Vec<FaultRecord>> = candidates
+        .iter()
+        .cycle()
+        .take(1000)
+        .map(|record| vec![record.clone()])
+        .collect();
+
+    // Same instruction limit for both variants
+    attack.config.fault_instruction_limit = Some(2000);
+
+    let mut group = c.benchmark_group("attack_path");
+    group.sample_size(10);
+    // Code is written to a new instance for every attack
+    group.bench_function("1000 attacks, 256KB segment, new instance", |b| {
+        b.iter(|| {
+            for records in &attacks {
+                let mut simulation = Control::new(&attack.file_data, &attack.config).unwrap();
+                simulation.set_instruction_limit(2000);
+                simulation
+                    .run_with_faults(RunType::Run, false, records)
+                    .unwrap();
+            }
+        })
+    });
+    // Simulation instances are reused for the attacks of each rayon job
+    group.bench_function("1000 attacks, 256KB segment, reused instance", |b| {
+        b.iter(|| attack.run_faults_parallel(2000, &attacks, 1).unwrap())
+    });
+}
+
+criterion_group!(
+    benches,
+    criterion_benchmark,
+    code_loading_benchmark,
+    attack_path_benchmark
+);
 criterion_main!(benches);
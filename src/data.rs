@@ -0,0 +1,256 @@
+//! Result types of a simulation which only depend on `core` and `alloc`
+//!
+//! The simulation uses them with the executable fault type (`FaultType`). Without
+//! the `std` feature they can be used with a data-only fault descriptor, e.g. the
+//! name of the fault as `String` (see `FaultRecord::descriptor`)
+use alloc::{format, string::String, vec::Vec};
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+
+/// Supported target architectures
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TargetArch {
+    /// ARMv8-M core (e.g. Cortex-M33) executing Thumb code
+    #[default]
+    CortexM,
+    /// ARMv8-A core executing 64 bit code
+    AArch64,
+    /// ARMv7-A/R core executing mixed ARM and Thumb code (not detected from the elf file)
+    Arm,
+}
+
+#[derive(Clone, Debug)]
+/// Representation for a fault which shall be executed at step `index` of a simulation.
+pub struct FaultRecord<F> {
+    pub index: usize,
+    pub fault_type: F,
+}
+
+#[derive(Clone, Debug, Eq)]
+/// One recorded step of a simulation
+pub enum TraceRecord {
+    Instruction {
+        address: u64,
+        index: usize,
+        asm_instruction: Vec<u8>,
+        /// Instruction was executed in Thumb state
+        thumb: bool,
+        registers: Option<[u32; 17]>,
+    },
+    Fault {
+        address: u64,
+        fault_type: String,
+        data: Vec<u8>,
+    },
+}
+
+impl PartialEq for TraceRecord {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                TraceRecord::Instruction {
+                    address: address1,
+                    index: _,
+                    asm_instruction: _,
+                    thumb: _,
+                    registers: _,
+                },
+                TraceRecord::Instruction {
+                    address: address2,
+                    index: _,
+                    asm_instruction: _,
+                    thumb: _,
+                    registers: _,
+                },
+            ) => address1 == address2,
+            _ => false,
+        }
+    }
+}
+
+impl Hash for TraceRecord {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        if let TraceRecord::Instruction { address, .. } = self {
+            address.hash(hasher);
+        }
+    }
+}
+
+/// Write access to a watched memory range
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatchRecord {
+    /// Address of the writing instruction
+    pub pc: u64,
+    /// Written address
+    pub address: u64,
+    /// Memory content before the write
+    pub old_data: Vec<u8>,
+    /// Written data
+    pub new_data: Vec<u8>,
+}
+
+/// Memory write of the program
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WriteRecord {
+    /// Address of the writing instruction
+    pub pc: u64,
+    /// Written address
+    pub address: u64,
+    /// Size of the access in bytes
+    pub size: usize,
+    /// Written value
+    pub value: u64,
+}
+
+impl TraceRecord {
+    pub fn address(&self) -> u64 {
+        match self {
+            TraceRecord::Instruction { address, .. } => *address,
+            TraceRecord::Fault { address, .. } => *address,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Representation of an fault which was executed in a simulation.
+pub struct FaultData<F> {
+    /// The original instructions which would have been performed without the fault.
+    pub original_instruction: Vec<u8>,
+    /// The modified instructions if it was changed which is performed with the fault.
+    pub modified_instruction: Vec<u8>,
+    /// Size of the instruction at the fault address in bytes.
+    pub instruction_size: usize,
+    /// The original register value if a register was changed by the fault.
+    pub original_register_value: Option<u64>,
+    /// The register state (R0-R12, SP, LR, PC, CPSR) when the attack was successful.
+    /// Only captured for successful attacks on 32 bit ARM targets.
+    pub final_registers: Option<[u64; 17]>,
+    /// Name of the trigger of the successful decision ("auth" for the auth address).
+    pub trigger: Option<String>,
+    /// Emulation time of the attack run which contained this fault.
+    /// Only measured for successful attacks.
+    pub run_duration: Duration,
+    /// The code at the fault address was executed in Thumb state.
+    pub thumb: bool,
+    /// Target architecture of the simulation which injected the fault.
+    pub target_arch: TargetArch,
+    /// The recorded execution trace of this fault.
+    pub record: TraceRecord,
+    /// FaultRecord which caused this FaultData to be simulated.
+    pub fault: FaultRecord<F>,
+}
+
+impl<F> FaultData<F> {
+    /// Create fault data of an executed fault
+    ///
+    /// The instruction size is taken from the original instruction. Register value,
+    /// final registers, trigger and run duration are not set. Thumb state and target
+    /// architecture default to Cortex-M and are set when the fault is injected
+    pub fn new(
+        record: TraceRecord,
+        fault: FaultRecord<F>,
+        original_instruction: Vec<u8>,
+        modified_instruction: Vec<u8>,
+    ) -> Self {
+        Self {
+            instruction_size: original_instruction.len(),
+            original_instruction,
+            modified_instruction,
+            original_register_value: None,
+            final_registers: None,
+            trigger: None,
+            run_duration: Duration::ZERO,
+            thumb: true,
+            target_arch: TargetArch::default(),
+            record,
+            fault,
+        }
+    }
+
+    /// Generate out of a FaultData array a FaultRecord array
+    pub fn get_simulation_fault_records(fault_data_records: &[FaultData<F>]) -> Vec<FaultRecord<F>>
+    where
+        F: Clone,
+    {
+        fault_data_records
+            .iter()
+            .map(|record| record.fault.clone())
+            .collect()
+    }
+
+    /// Check if only the first half of a 32 bit instruction at the given address is changed
+    ///
+    /// The remaining half may still decode to a valid but different instruction
+    pub fn changes_first_half_only(&self, address: u64) -> bool {
+        if self.instruction_size != 4 {
+            return false;
+        }
+        let start = self.record.address().wrapping_sub(address);
+        let mut changed = self
+            .original_instruction
+            .iter()
+            .zip(self.modified_instruction.iter())
+            .enumerate()
+            .filter(|(_, (original, modified))| original != modified)
+            .map(|(offset, _)| start.wrapping_add(offset as u64))
+            .peekable();
+        changed.peek().is_some() && changed.all(|offset| offset < 2)
+    }
+
+    /// Compare the injected bytes with the ones of another fault
+    ///
+    /// Returns (offset, own byte, other byte) of all differing bytes, bytes
+    /// beyond the shorter modification are compared with 0
+    pub fn diff(&self, other: &FaultData<F>) -> Vec<(usize, u8, u8)> {
+        let length = self
+            .modified_instruction
+            .len()
+            .max(other.modified_instruction.len());
+        (0..length)
+            .filter_map(|offset| {
+                let own = self.modified_instruction.get(offset).copied().unwrap_or(0);
+                let other = other.modified_instruction.get(offset).copied().unwrap_or(0);
+                (own != other).then_some((offset, own, other))
+            })
+            .collect()
+    }
+}
+
+/// Faults are equal if the same bytes are injected at the same address
+impl<F> PartialEq for FaultData<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.record.address() == other.record.address()
+            && self.modified_instruction == other.modified_instruction
+    }
+}
+
+/// One line with address, fault and the changed bytes (or the original register value)
+impl<F: fmt::Display> fmt::Display for FaultData<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "0x{:08X} {}",
+            self.record.address(),
+            self.fault.fault_type
+        )?;
+        if !self.modified_instruction.is_empty() {
+            write!(
+                f,
+                ": {} -> {}",
+                hex(&self.original_instruction),
+                hex(&self.modified_instruction)
+            )?;
+        } else if let Some(value) = self.original_register_value {
+            write!(f, ": register was 0x{:08x}", value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Format bytes as hex string
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
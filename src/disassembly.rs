@@ -4,7 +4,11 @@ use std::{
     path::Path,
 };
 
-use crate::simulation::{fault_data::FaultData, record::TraceRecord};
+use crate::simulation::{
+    cpu::{Endianness, TargetArch},
+    fault_data::FaultData,
+    record::TraceRecord,
+};
 use addr2line::{fallible_iterator::FallibleIterator, gimli};
 use capstone::prelude::*;
 use colored::Colorize;
@@ -12,6 +16,8 @@ use regex::Regex;
 
 pub struct Disassembly {
     cs: Capstone,
+    target_arch: TargetArch,
+    endianness: Endianness,
 }
 
 impl Default for Disassembly {
@@ -22,15 +28,76 @@ impl Default for Disassembly {
 
 impl Disassembly {
     pub fn new() -> Self {
+        Self::with_arch(TargetArch::CortexM)
+    }
+
+    /// Create disassembler for the given little endian target architecture
+    pub fn with_arch(target_arch: TargetArch) -> Self {
+        Self::with_target(target_arch, Endianness::Little)
+    }
+
+    /// Create disassembler for code executed in the given state of the target
+    ///
+    /// ARM targets decode Thumb code in Thumb state, other targets have one state only
+    pub fn with_state(target_arch: TargetArch, thumb: bool) -> Self {
+        if target_arch != TargetArch::Arm || !thumb {
+            return Self::with_arch(target_arch);
+        }
         let cs = Capstone::new()
             .arm()
             .mode(arch::arm::ArchMode::Thumb)
-            .extra_mode([arch::arm::ArchExtraMode::MClass].iter().copied())
+            .endian(capstone::Endian::Little)
             .detail(true)
             .build()
             .expect("Failed to create Capstone object");
 
-        Self { cs } // Define regex to extract register number from instruction
+        Self {
+            cs,
+            target_arch,
+            endianness: Endianness::Little,
+        }
+    }
+
+    /// Create disassembler for the given target architecture and byte order
+    ///
+    /// Instructions are little endian on all supported targets (BE8), the byte
+    /// order only applies to data
+    pub fn with_target(target_arch: TargetArch, endianness: Endianness) -> Self {
+        let endian = capstone::Endian::Little;
+        let cs = match target_arch {
+            TargetArch::CortexM => Capstone::new()
+                .arm()
+                .mode(arch::arm::ArchMode::Thumb)
+                .extra_mode([arch::arm::ArchExtraMode::MClass].iter().copied())
+                .endian(endian)
+                .detail(true)
+                .build(),
+            // Mixed images are disassembled as ARM code
+            TargetArch::Arm => Capstone::new()
+                .arm()
+                .mode(arch::arm::ArchMode::Arm)
+                .endian(endian)
+                .detail(true)
+                .build(),
+            TargetArch::AArch64 => Capstone::new()
+                .arm64()
+                .mode(arch::arm64::ArchMode::Arm)
+                .endian(endian)
+                .detail(true)
+                .build(),
+        }
+        .expect("Failed to create Capstone object");
+
+        Self {
+            cs,
+            target_arch,
+            endianness,
+        }
+    }
+
+    /// Get target architecture and byte order of the disassembler
+    pub fn target(&self) -> (TargetArch, Endianness) {
+        (self.target_arch, self.endianness)
     }
 
     // Check if register is used in given instruction
@@ -42,6 +109,29 @@ impl Disassembly {
             .contains(format!("r{}", register).as_str())
     }
 
+    /// Disassemble instruction bytes at the given address into a readable string
+    ///
+    /// Multiple instructions are separated by "; ". Data which can not be
+    /// decoded results in an empty string
+    pub fn instructions_to_string(&self, data: &[u8], address: u64) -> String {
+        match self.cs.disasm_all(data, address) {
+            Ok(insns) => insns
+                .iter()
+                .map(|ins| {
+                    format!(
+                        "{} {}",
+                        ins.mnemonic().unwrap_or_default(),
+                        ins.op_str().unwrap_or_default()
+                    )
+                    .trim_end()
+                    .to_string()
+                })
+                .collect::<Vec<String>>()
+                .join("; "),
+            Err(_) => String::new(),
+        }
+    }
+
     /// Disassemble fault data structure
     fn disassembly_fault_data(
         &self,
@@ -110,13 +200,10 @@ impl Disassembly {
                         // Print register and flags get next trace record::Instruction
                         for next_trace_record in iter.clone() {
                             if let TraceRecord::Instruction { registers, .. } = next_trace_record {
-                                // Allways print CPU flags
-                                print_flags_and_registers(
-                                    &re,
-                                    &registers.unwrap(),
-                                    old_registers,
-                                    ins,
-                                );
+                                // Allways print CPU flags if register data is recorded
+                                if let Some(registers) = registers {
+                                    print_flags_and_registers(&re, registers, old_registers, ins);
+                                }
                                 break;
                             }
                         }
@@ -1,3 +1,4 @@
+use crate::simulation::cpu::clear_thumb;
 use addr2line::{gimli, object::read, Context};
 use elf::{
     endian::AnyEndian, file::FileHeader, section::SectionHeader, segment::ProgramHeader,
@@ -9,9 +10,12 @@ pub use elf::abi::*;
 
 pub struct ElfFile {
     pub header: FileHeader<AnyEndian>,
+    /// Start address of the program, bit 0 is set for Thumb code
+    pub entry_point: u64,
     pub program_data: Vec<(ProgramHeader, Vec<u8>)>,
     pub section_map: HashMap<String, SectionHeader>,
     pub symbol_map: HashMap<String, Symbol>,
+    symbols: Vec<(String, Symbol)>,
     file_data: Vec<u8>,
 }
 
@@ -26,7 +30,7 @@ impl ElfFile {
             .segments()
             .unwrap()
             .iter()
-            // TODO: Filter PT_LOAD sections
+            // Only loadable segments are mapped into the simulation
             .filter(|ph| ph.p_type == PT_LOAD)
             .map(|ph| (ph, elf_data.segment_data(&ph).unwrap().to_vec()))
             .collect();
@@ -60,6 +64,25 @@ impl ElfFile {
             })
             .collect();
 
+        // List all named symbols sorted by address
+        let mut symbols: Vec<(String, Symbol)> = common
+            .symtab
+            .as_ref()
+            .unwrap()
+            .iter()
+            .filter(|sym| sym.st_name != 0 && sym.st_symtype() != STT_FILE)
+            .map(|sym| {
+                (
+                    strtab
+                        .get(sym.st_name as usize)
+                        .expect("should parse")
+                        .to_string(),
+                    sym,
+                )
+            })
+            .collect();
+        symbols.sort_by_key(|(_, sym)| sym.st_value);
+
         // Sum Strings with their symbol into a hashmap
         let symbol_map: HashMap<String, Symbol> = common
             .symtab
@@ -79,13 +102,45 @@ impl ElfFile {
 
         // Fill struct
         Ok(Self {
+            entry_point: entry_point(elf_data.ehdr.e_entry, &program_data),
             header: elf_data.ehdr,
             program_data,
             section_map,
             symbol_map,
+            symbols,
             file_data,
         })
     }
+
+    /// Get all named symbols (name, symbol) sorted by address
+    pub fn symbols(&self) -> &[(String, Symbol)] {
+        &self.symbols
+    }
+
+    /// Get function containing the address as "name+0xoffset"
+    ///
+    /// The nearest function symbol at or below the address is taken, the Thumb bit
+    /// of the symbol address is ignored
+    pub fn function_at(&self, address: u64) -> Option<String> {
+        self.symbols
+            .iter()
+            .rev()
+            .find(|(_, sym)| sym.st_symtype() == STT_FUNC && clear_thumb(sym.st_value) <= address)
+            .map(|(name, sym)| match address - clear_thumb(sym.st_value) {
+                0 => name.clone(),
+                offset => format!("{}+0x{:x}", name, offset),
+            })
+    }
+
+    /// Find symbol by name, global symbols are preferred over local ones
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbol_map.get(name).or_else(|| {
+            self.symbols
+                .iter()
+                .find(|(symbol_name, _)| symbol_name == name)
+                .map(|(_, symbol)| symbol)
+        })
+    }
     pub fn get_debug_context(
         &self,
     ) -> Context<gimli::EndianReader<gimli::RunTimeEndian, std::rc::Rc<[u8]>>> {
@@ -93,6 +148,20 @@ impl ElfFile {
     }
 }
 
+/// Get start address of the program
+///
+/// If the file header has no entry point, the start of the first executable
+/// segment is taken
+fn entry_point(e_entry: u64, program_data: &[(ProgramHeader, Vec<u8>)]) -> u64 {
+    if e_entry != 0 {
+        return e_entry;
+    }
+    program_data
+        .iter()
+        .find(|(segment, _)| segment.p_flags & PF_X != 0)
+        .map_or(0, |(segment, _)| segment.p_paddr)
+}
+
 #[cfg(test)]
 mod tests {
     use addr2line::object::elf::PT_LOAD;
@@ -118,6 +187,22 @@ mod tests {
         assert!(elf_struct.symbol_map.get("serial_puts").is_some());
         assert!(elf_struct.symbol_map.get("decisiondata").is_some());
 
+        // Symbol table
+        assert!(elf_struct.symbol("decision_activation").is_some());
+        assert!(elf_struct.symbol("no_such_symbol").is_none());
+        assert_eq!(elf_struct.function_at(0x8000628).as_deref(), Some("main"));
+        assert_eq!(
+            elf_struct.function_at(0x8000630).as_deref(),
+            Some("main+0x8")
+        );
+        assert!(elf_struct
+            .symbols()
+            .windows(2)
+            .all(|pair| pair[0].1.st_value <= pair[1].1.st_value));
+
+        // Entry point
+        assert_eq!(elf_struct.entry_point, elf_struct.header.e_entry);
+
         //        assert_eq!(elf_struct.symbol_map["decision_activation"].st_name, 0xec);
         // assert_eq!(
         //     elf_struct.symbol_map["decision_activation"].st_value,
@@ -127,4 +212,26 @@ mod tests {
         // assert_eq!(elf_struct.symbol_map["decision_activation"].st_shndx, 1);
         // assert_eq!(elf_struct.symbol_map["decision_activation"].st_bind(), 1);
     }
+
+    /// Write victim_.elf with a different entry point of the file header
+    fn victim_with_entry(entry: u32) -> std::path::PathBuf {
+        let mut data = std::fs::read("tests/bin/victim_.elf").unwrap();
+        // e_entry of a 32 bit little endian file header
+        data[0x18..0x1c].copy_from_slice(&entry.to_le_bytes());
+        let path = std::env::temp_dir().join(format!("fault_simulator_entry_{:x}.elf", entry));
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn entry_point_fallback() {
+        // Entry point differing from the segment start (e.g. behind a vector table)
+        let elf_struct = ElfFile::new(victim_with_entry(0x8000629)).unwrap();
+        assert_ne!(elf_struct.entry_point, elf_struct.program_data[0].0.p_paddr);
+        assert_eq!(elf_struct.entry_point, 0x8000629);
+        // No entry point in the file header
+        let elf_struct = ElfFile::new(victim_with_entry(0)).unwrap();
+        assert_eq!(elf_struct.header.e_entry, 0);
+        assert_eq!(elf_struct.entry_point, elf_struct.program_data[0].0.p_paddr);
+    }
 }
@@ -0,0 +1,251 @@
+use super::{Disassembly, FaultFunctions, FaultType};
+use crate::simulation::{
+    cpu::{Cpu, TargetArch},
+    fault_data::FaultData,
+    record::{FaultRecord, TraceRecord},
+};
+use log::debug;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Branch flip fault structure
+///
+/// The condition of a conditional branch is inverted before execution, so the
+/// branch takes the opposite direction as with a glitch on the condition flags
+#[derive(Clone, Copy)]
+pub struct BranchFlip;
+
+impl Debug for BranchFlip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Branch Flip (branchflip)")
+    }
+}
+
+/// Implementation for BranchFlip fault
+impl BranchFlip {
+    /// Create a new BranchFlip fault
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self)
+    }
+}
+
+/// Read little endian halfword or word of an instruction
+///
+/// Instructions are little endian on all supported targets (BE8)
+fn read_value(data: &[u8]) -> u32 {
+    data.iter()
+        .rev()
+        .fold(0, |value, byte| (value << 8) | *byte as u32)
+}
+
+/// Flip one bit of a little endian halfword or word of an instruction
+fn flip_bit(data: &mut [u8], bit: usize) {
+    data[bit / 8] ^= 1 << (bit % 8);
+}
+
+/// Invert the condition of a conditional branch
+///
+/// Supported are B<cond>, CBZ and CBNZ for Thumb, B<cond> for ARM and B.<cond>,
+/// CBZ, CBNZ, TBZ and TBNZ for AArch64. Returns None for other instructions
+fn flip_condition(instruction: &[u8], thumb: bool, target_arch: TargetArch) -> Option<Vec<u8>> {
+    let mut modified = instruction.to_vec();
+    if thumb {
+        let first = read_value(instruction.get(..2)?);
+        match instruction.len() {
+            // B<cond> (T1), condition in bits 11:8
+            2 if first & 0xf000 == 0xd000 && (first >> 9) & 0x7 != 0x7 => {
+                flip_bit(&mut modified[..2], 8)
+            }
+            // CBZ / CBNZ, operation in bit 11
+            2 if first & 0xf500 == 0xb100 => flip_bit(&mut modified[..2], 11),
+            // B<cond>.W (T3), condition in bits 9:6 of the first halfword
+            4 if first & 0xf800 == 0xf000
+                && read_value(&instruction[2..]) & 0xd000 == 0x8000
+                && (first >> 7) & 0x7 != 0x7 =>
+            {
+                flip_bit(&mut modified[..2], 6)
+            }
+            _ => return None,
+        }
+    } else {
+        if instruction.len() != 4 {
+            return None;
+        }
+        let word = read_value(instruction);
+        match target_arch {
+            // B.<cond>, condition in bits 3:0
+            TargetArch::AArch64 if word & 0xff000010 == 0x54000000 && word & 0xe != 0xe => {
+                flip_bit(&mut modified, 0)
+            }
+            // CBZ / CBNZ and TBZ / TBNZ, operation in bit 24
+            TargetArch::AArch64
+                if word & 0x7e000000 == 0x34000000 || word & 0x7e000000 == 0x36000000 =>
+            {
+                flip_bit(&mut modified, 24)
+            }
+            // B<cond> / BL<cond>, condition in bits 31:28
+            TargetArch::Arm | TargetArch::CortexM
+                if word >> 29 != 0x7 && (word >> 25) & 0x7 == 0x5 =>
+            {
+                flip_bit(&mut modified, 28)
+            }
+            _ => return None,
+        }
+    }
+    Some(modified)
+}
+
+impl FaultFunctions for BranchFlip {
+    /// Invert the condition of the branch before execution
+    ///
+    /// Instructions which are no conditional branch in the current state are
+    /// not modified
+    /// Return
+    ///     false:  No code repair needed
+    ///     true:   Code repair after fault injection is required
+    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> Result<bool, String> {
+        // Get current assembler instruction
+        let (address, original_instruction) = cpu.asm_cmd_read();
+
+        let Some(modified_instruction) = flip_condition(
+            &original_instruction,
+            cpu.is_thumb_state(),
+            cpu.get_target_arch(),
+        ) else {
+            debug!(
+                "{:?}: no conditional branch at 0x{:X} ({:02x?})",
+                self, address, original_instruction
+            );
+            return Ok(false);
+        };
+        cpu.asm_cmd_write(address, &modified_instruction)
+            .map_err(|e| format!("Failed to write instruction at 0x{:X}: {:?}", address, e))?;
+
+        let record = TraceRecord::Fault {
+            address,
+            fault_type: format!(
+                "Branch Flip (branchflip) {:02x?} -> {:02x?}",
+                original_instruction, modified_instruction
+            ),
+            data: original_instruction.clone(),
+        };
+        cpu.get_trace_data().push(record.clone());
+
+        // Push to fault data vector
+        cpu.get_fault_data().push(FaultData::new(
+            record,
+            fault.clone(),
+            original_instruction,
+            modified_instruction,
+        ));
+
+        // Trigger code repair after fault injection
+        Ok(true)
+    }
+
+    /// Filtering of traces to reduce the number of traces to analyze
+    ///
+    /// Only conditional branches are kept. Code of ARM targets may be ARM or
+    /// Thumb code, so the instruction is decoded in the recorded state
+    fn filter(&self, records: &mut Vec<TraceRecord>, cs: &Disassembly) {
+        let (target_arch, _) = cs.target();
+        records.retain(|record| match record {
+            TraceRecord::Instruction {
+                asm_instruction,
+                thumb,
+                ..
+            } => flip_condition(asm_instruction, *thumb, target_arch).is_some(),
+            _ => false,
+        });
+    }
+
+    /// Short description of the fault for logging
+    fn description(&self) -> String {
+        "branch flip".to_string()
+    }
+
+    /// Try to parse a BranchFlip fault from a string
+    fn try_from(&self, input: &str) -> Option<FaultType> {
+        if input == "branchflip" {
+            return Some(Self::new());
+        }
+        None
+    }
+
+    /// Get the name of the fault type
+    fn name(&self) -> &'static str {
+        "Branch Flip"
+    }
+
+    /// Get the list of possible/good faults
+    fn get_list(&self) -> Vec<String> {
+        vec!["branchflip".to_string()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flip_branch_conditions() {
+        // "beq" -> "bne"
+        assert_eq!(
+            flip_condition(&[0x02, 0xd0], true, TargetArch::CortexM),
+            Some(vec![0x02, 0xd1])
+        );
+        // "cbz r0" -> "cbnz r0"
+        assert_eq!(
+            flip_condition(&[0x08, 0xb1], true, TargetArch::CortexM),
+            Some(vec![0x08, 0xb9])
+        );
+        // "bne.w" -> "beq.w"
+        assert_eq!(
+            flip_condition(&[0x40, 0xf0, 0x02, 0x80], true, TargetArch::CortexM),
+            Some(vec![0x00, 0xf0, 0x02, 0x80])
+        );
+        // Thumb "beq" is no conditional branch in ARM state
+        assert!(flip_condition(&[0x02, 0xd0, 0x00, 0x00], false, TargetArch::Arm).is_none());
+        // ARM "beq" -> "bne"
+        assert_eq!(
+            flip_condition(&[0x02, 0x00, 0x00, 0x0a], false, TargetArch::Arm),
+            Some(vec![0x02, 0x00, 0x00, 0x1a])
+        );
+        // AArch64 "b.eq" -> "b.ne"
+        assert_eq!(
+            flip_condition(&[0x40, 0x00, 0x00, 0x54], false, TargetArch::AArch64),
+            Some(vec![0x41, 0x00, 0x00, 0x54])
+        );
+        // No conditional branches: "nop", "b" (T2), ARM "b"
+        assert!(flip_condition(&[0x00, 0xbf], true, TargetArch::CortexM).is_none());
+        assert!(flip_condition(&[0x02, 0xe0], true, TargetArch::CortexM).is_none());
+        assert!(flip_condition(&[0x02, 0x00, 0x00, 0xea], false, TargetArch::Arm).is_none());
+    }
+
+    #[test]
+    fn filter_in_recorded_state() {
+        let record = |asm_instruction: &[u8], thumb| TraceRecord::Instruction {
+            address: 0x8000,
+            index: 0,
+            asm_instruction: asm_instruction.to_vec(),
+            thumb,
+            registers: None,
+        };
+        // ARM "beq" in ARM state, the same bytes are no branch in Thumb state
+        let mut records = vec![
+            record(&[0x02, 0x00, 0x00, 0x0a], false),
+            record(&[0x02, 0x00, 0x00, 0x0a], true),
+            record(&[0x02, 0xd0], true),
+        ];
+        BranchFlip.filter(&mut records, &Disassembly::with_arch(TargetArch::Arm));
+        assert_eq!(records.len(), 2);
+        assert!(matches!(
+            records[0],
+            TraceRecord::Instruction { thumb: false, .. }
+        ));
+        assert!(matches!(
+            records[1],
+            TraceRecord::Instruction { thumb: true, .. }
+        ));
+    }
+}
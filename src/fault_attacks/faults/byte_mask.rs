@@ -0,0 +1,190 @@
+use super::{Disassembly, FaultFunctions, FaultType};
+use crate::simulation::{
+    cpu::Cpu,
+    fault_data::FaultData,
+    record::{FaultRecord, TraceRecord},
+};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Byte mask fault structure
+/// offset      Byte lane of the instruction (0 = first byte in memory)
+/// and_mask    Mask which is ANDed to the byte
+/// or_mask     Mask which is ORed to the byte after the AND mask
+///
+#[derive(Clone, Copy)]
+pub struct ByteMask {
+    pub offset: usize,
+    pub and_mask: u8,
+    pub or_mask: u8,
+}
+
+impl Debug for ByteMask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Byte Mask (bytemask_{}_{:02x}_{:02x})",
+            self.offset, self.and_mask, self.or_mask
+        )
+    }
+}
+
+/// Implementation for ByteMask fault
+impl ByteMask {
+    /// Create a new ByteMask fault
+    pub fn new(offset: usize, and_mask: u8, or_mask: u8) -> Arc<Self> {
+        Arc::new(Self {
+            offset,
+            and_mask,
+            or_mask,
+        })
+    }
+
+    /// Apply the masks to the byte lane of the instruction
+    ///
+    /// Instructions are little endian on all supported targets (BE8), so the lane
+    /// is the same for all byte orders. Returns None if the lane is not part of
+    /// the instruction
+    fn apply(&self, instruction: &[u8]) -> Option<Vec<u8>> {
+        let mut modified = instruction.to_vec();
+        let byte = modified.get_mut(self.offset)?;
+        *byte = (*byte & self.and_mask) | self.or_mask;
+        Some(modified)
+    }
+}
+
+impl FaultFunctions for ByteMask {
+    /// Apply the masks to one byte of the instruction before execution
+    /// Return
+    ///     false:  No code repair needed
+    ///     true:   Code repair after fault injection is required
+    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> Result<bool, String> {
+        // Get current assembler instruction
+        let (address, original_instruction) = cpu.asm_cmd_read();
+
+        let modified_instruction = self.apply(&original_instruction).ok_or_else(|| {
+            format!(
+                "{:?}: byte {} is outside of the {} byte instruction at 0x{:X}",
+                self,
+                self.offset,
+                original_instruction.len(),
+                address
+            )
+        })?;
+        cpu.asm_cmd_write(address, &modified_instruction)
+            .map_err(|e| format!("Failed to write instruction at 0x{:X}: {:?}", address, e))?;
+
+        let record = TraceRecord::Fault {
+            address,
+            fault_type: format!(
+                "Byte Mask (bytemask_{}_{:02x}_{:02x}) {:02x?} -> {:02x?}",
+                self.offset,
+                self.and_mask,
+                self.or_mask,
+                original_instruction,
+                modified_instruction
+            ),
+            data: original_instruction.clone(),
+        };
+        cpu.get_trace_data().push(record.clone());
+
+        // Push to fault data vector
+        cpu.get_fault_data().push(FaultData::new(
+            record,
+            fault.clone(),
+            original_instruction,
+            modified_instruction,
+        ));
+
+        // Trigger code repair after fault injection
+        Ok(true)
+    }
+
+    /// Filtering of traces to reduce the number of traces to analyze
+    ///
+    /// Only instructions which contain the byte lane are kept
+    fn filter(&self, records: &mut Vec<TraceRecord>, _cs: &Disassembly) {
+        records.retain(|record| match record {
+            TraceRecord::Instruction {
+                asm_instruction, ..
+            } => self.offset < asm_instruction.len(),
+            _ => false,
+        });
+    }
+
+    /// Short description of the fault for logging
+    fn description(&self) -> String {
+        format!(
+            "bytemask &0x{:02x} |0x{:02x}@byte{}",
+            self.and_mask, self.or_mask, self.offset
+        )
+    }
+
+    /// Try to parse a ByteMask fault from a string
+    fn try_from(&self, input: &str) -> Option<FaultType> {
+        // divide name from attributes
+        let collect: Vec<&str> = input.split('_').collect();
+        // check if name and attributes are present
+        let fault_type = collect.first().copied()?;
+        let attribute_1 = collect.get(1).copied()?;
+        let attribute_2 = collect.get(2).copied()?;
+        let attribute_3 = collect.get(3).copied()?;
+        // check if fault type is byte mask
+        if fault_type == "bytemask" {
+            // check if attributes are valid values
+            if let (Ok(offset), Ok(and_mask), Ok(or_mask)) = (
+                attribute_1.parse::<usize>(),
+                u8::from_str_radix(attribute_2, 16),
+                u8::from_str_radix(attribute_3, 16),
+            ) {
+                // return ByteMask struct
+                return Some(Self::new(offset, and_mask, or_mask));
+            }
+        }
+        None
+    }
+
+    /// Get the name of the fault type
+    fn name(&self) -> &'static str {
+        "Byte Mask"
+    }
+
+    /// Get the list of possible/good faults
+    fn get_list(&self) -> Vec<String> {
+        let mut list = Vec::new();
+        // Generate a list of stuck-at nibbles for the lower byte lanes
+        // Values will look like: bytemask_0_0f_00, bytemask_0_f0_00, bytemask_0_ff_0f, ...
+        for offset in 0..2 {
+            for (and_mask, or_mask) in [(0x0fu8, 0x00u8), (0xf0, 0x00), (0xff, 0x0f), (0xff, 0xf0)]
+            {
+                list.push(format!(
+                    "bytemask_{}_{:02x}_{:02x}",
+                    offset, and_mask, or_mask
+                ));
+            }
+        }
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_byte_lane() {
+        let fault = ByteMask {
+            offset: 1,
+            and_mask: 0x0f,
+            or_mask: 0x40,
+        };
+        assert_eq!(fault.apply(&[0x12, 0xbf]), Some(vec![0x12, 0x4f]));
+        assert_eq!(
+            fault.apply(&[0xff, 0xff, 0x00, 0x00]),
+            Some(vec![0xff, 0x4f, 0x00, 0x00])
+        );
+        // Lane outside of a 16 bit instruction
+        let fault = ByteMask { offset: 2, ..fault };
+        assert!(fault.apply(&[0x12, 0xbf]).is_none());
+    }
+}
@@ -0,0 +1,162 @@
+use super::{Disassembly, FaultFunctions, FaultType};
+use crate::simulation::{
+    cpu::Cpu,
+    fault_data::FaultData,
+    record::{FaultRecord, TraceRecord},
+};
+use log::debug;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Byte set fault structure
+/// offset  Start of the overwritten region relative to the fault address (program counter)
+/// value   Byte pattern which is written to the region
+/// len     Number of overwritten bytes
+///
+/// The region is limited to the instruction at the fault address. The code is
+/// restored after this instruction, so following instructions are never executed
+/// in their faulted state
+///
+#[derive(Clone, Copy)]
+pub struct ByteSet {
+    pub offset: usize,
+    pub value: u8,
+    pub len: usize,
+}
+
+impl Debug for ByteSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Byte Set (byteset_{}_{:02x}_{})",
+            self.offset, self.value, self.len
+        )
+    }
+}
+
+/// Implementation for ByteSet fault
+impl ByteSet {
+    /// Create a new ByteSet fault
+    pub fn new(offset: usize, value: u8, len: usize) -> Arc<Self> {
+        Arc::new(Self { offset, value, len })
+    }
+}
+
+impl FaultFunctions for ByteSet {
+    /// Overwrite the instruction region with the byte pattern before execution
+    /// Return
+    ///     false:  No code repair needed
+    ///     true:   Code repair after fault injection is required
+    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> Result<bool, String> {
+        // Region starts relative to the fault address
+        let program_counter = cpu.get_program_counter();
+        let address = program_counter + self.offset as u64;
+        let instruction_size = cpu.get_asm_cmd_size(program_counter).map_err(|e| {
+            format!(
+                "Failed to read instruction at 0x{:X}: {:?}",
+                program_counter, e
+            )
+        })?;
+        // Only the current instruction is executed before the code is restored
+        let Some(len) = instruction_size
+            .checked_sub(self.offset)
+            .map(|remaining| remaining.min(self.len))
+            .filter(|len| *len != 0)
+        else {
+            debug!(
+                "Byte set region {}..{} is outside of the instruction at 0x{:X}",
+                self.offset,
+                self.offset + self.len,
+                program_counter
+            );
+            return Ok(false);
+        };
+
+        // Read original data of the region
+        let mut original_instruction = vec![0; len];
+        cpu.memory_read(address, &mut original_instruction)
+            .map_err(|e| format!("Failed to read memory at 0x{:X}: {:?}", address, e))?;
+
+        // Apply byte pattern to the region
+        let modified_instruction = vec![self.value; len];
+        cpu.asm_cmd_write(address, &modified_instruction)
+            .map_err(|e| format!("Failed to write instruction at 0x{:X}: {:?}", address, e))?;
+
+        let record = TraceRecord::Fault {
+            address,
+            fault_type: format!(
+                "Byte Set (byteset_{}_{:02x}_{}) {:02x?} -> {:02x?}",
+                self.offset, self.value, self.len, original_instruction, modified_instruction
+            ),
+            data: original_instruction.clone(),
+        };
+        cpu.get_trace_data().push(record.clone());
+
+        // Push to fault data vector
+        cpu.get_fault_data().push(FaultData {
+            instruction_size,
+            ..FaultData::new(
+                record,
+                fault.clone(),
+                original_instruction,
+                modified_instruction,
+            )
+        });
+
+        // Trigger code repair after fault injection
+        Ok(true)
+    }
+
+    /// Filtering of traces to reduce the number of traces to analyze
+    fn filter(&self, _records: &mut Vec<TraceRecord>, _cs: &Disassembly) {}
+
+    /// Short description of the fault for logging
+    fn description(&self) -> String {
+        format!(
+            "byteset 0x{:02x} x{}@byte{}",
+            self.value, self.len, self.offset
+        )
+    }
+
+    /// Try to parse a ByteSet fault from a string
+    fn try_from(&self, input: &str) -> Option<FaultType> {
+        // divide name from attributes
+        let collect: Vec<&str> = input.split('_').collect();
+        // check if name and attributes are present
+        let fault_type = collect.first().copied()?;
+        let attribute_1 = collect.get(1).copied()?;
+        let attribute_2 = collect.get(2).copied()?;
+        let attribute_3 = collect.get(3).copied()?;
+        // check if fault type is byte set
+        if fault_type == "byteset" {
+            // check if attributes are valid values
+            if let (Ok(offset), Ok(value), Ok(len)) = (
+                attribute_1.parse::<usize>(),
+                u8::from_str_radix(attribute_2, 16),
+                attribute_3.parse::<usize>(),
+            ) {
+                // return ByteSet struct
+                return Some(Self::new(offset, value, len));
+            }
+        }
+        None
+    }
+
+    /// Get the name of the fault type
+    fn name(&self) -> &'static str {
+        "Byte Set"
+    }
+
+    /// Get the list of possible/good faults
+    fn get_list(&self) -> Vec<String> {
+        let mut list = Vec::new();
+        // Generate a list of stuck-at faults for 16 and 32 bit instructions
+        // Values will look like: byteset_0_00_2, byteset_0_00_4, byteset_0_ff_2, ...
+        for value in [0x00u8, 0xff] {
+            for len in [2, 4] {
+                list.push(format!("byteset_0_{:02x}_{}", value, len));
+            }
+        }
+        list
+    }
+}
@@ -0,0 +1,178 @@
+use super::{Disassembly, FaultFunctions, FaultType};
+use crate::simulation::{
+    cpu::{instruction_cycles, Cpu},
+    fault_data::FaultData,
+    record::{FaultRecord, TraceRecord},
+};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Clock glitch fault structure
+/// cycle   Clock cycle of the glitch, counted from the start of the trace with the
+///         timing model of `SimulationConfig::count_cycles`
+///
+/// The instruction which executes during the glitched cycle is skipped
+#[derive(Clone, Copy)]
+pub struct ClockGlitch {
+    pub cycle: usize,
+}
+
+impl Debug for ClockGlitch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Clock Glitch (clock_{})", self.cycle)
+    }
+}
+
+/// Implementation for ClockGlitch fault
+impl ClockGlitch {
+    /// Create a new ClockGlitch fault
+    pub fn new(cycle: usize) -> Arc<Self> {
+        Arc::new(Self { cycle })
+    }
+}
+
+impl FaultFunctions for ClockGlitch {
+    /// Skip the instruction executing at the glitched cycle
+    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> Result<bool, String> {
+        let address = cpu.get_program_counter();
+        let instruction_size = cpu
+            .get_asm_cmd_size(address)
+            .map_err(|e| format!("Failed to read instruction at 0x{:X}: {:?}", address, e))?;
+        let modified_instruction = cpu.nop_cmd(instruction_size)?;
+        cpu.set_program_counter(address + instruction_size as u64);
+
+        let mut original_instruction = vec![0; instruction_size];
+        cpu.memory_read(address, &mut original_instruction)
+            .map_err(|e| format!("Failed to read instruction at 0x{:X}: {:?}", address, e))?;
+
+        let record = TraceRecord::Fault {
+            address,
+            fault_type: format!("Clock Glitch (cycle {})", self.cycle),
+            data: original_instruction.clone(),
+        };
+        cpu.get_trace_data().push(record.clone());
+
+        // Push to fault data vector
+        cpu.get_fault_data().push(FaultData::new(
+            record,
+            fault.clone(),
+            original_instruction,
+            modified_instruction,
+        ));
+
+        // No cleanup required
+        Ok(false)
+    }
+
+    /// Filtering of traces to reduce the number of traces to analyze
+    ///
+    /// Only the instruction executing at the cycle is kept. The cycles are summed up
+    /// over the trace, so without deep analysis (no repeated code in the trace) they
+    /// are only known up to the first repeated instruction
+    fn filter(&self, records: &mut Vec<TraceRecord>, cs: &Disassembly) {
+        let (target_arch, _) = cs.target();
+        let mut start = 0;
+        let mut position = None;
+        for (next_index, record) in records.iter().enumerate() {
+            if let TraceRecord::Instruction {
+                index,
+                asm_instruction,
+                ..
+            } = record
+            {
+                // Gap of a reduced trace
+                if *index != next_index {
+                    break;
+                }
+                let end = start + instruction_cycles(asm_instruction, target_arch);
+                if self.cycle < end {
+                    position = Some(next_index);
+                    break;
+                }
+                start = end;
+            }
+        }
+        match position {
+            Some(position) => {
+                records.drain(..position);
+                records.truncate(1);
+            }
+            None => records.clear(),
+        }
+    }
+
+    /// Short description of the fault for logging
+    fn description(&self) -> String {
+        format!("clock glitch @cycle{}", self.cycle)
+    }
+
+    /// Try to parse a ClockGlitch fault from a string
+    fn try_from(&self, input: &str) -> Option<FaultType> {
+        // divide name from attribute
+        let collect: Vec<&str> = input.split('_').collect();
+        // check if name and attribute are present
+        let fault_type = collect.first().copied()?;
+        let attribute = collect.get(1).copied()?;
+        // check if fault type is clock glitch
+        if fault_type == "clock" {
+            // check if attribute is a number
+            if let Ok(cycle) = attribute.parse::<usize>() {
+                // return ClockGlitch struct
+                return Some(Self::new(cycle));
+            }
+        }
+        None
+    }
+
+    /// Get the name of the fault type
+    fn name(&self) -> &'static str {
+        "Clock Glitch"
+    }
+
+    /// Get the list of possible/good faults
+    ///
+    /// The cycles depend on the program, so there are no suggested faults
+    fn get_list(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(index: usize, asm_instruction: &[u8]) -> TraceRecord {
+        TraceRecord::Instruction {
+            address: 0x8000000 + 2 * index as u64,
+            index,
+            asm_instruction: asm_instruction.to_vec(),
+            thumb: true,
+            registers: None,
+        }
+    }
+
+    #[test]
+    fn filter_instruction_at_cycle() {
+        let cs = Disassembly::new();
+        // "push {r7, lr}" (3 cycles), "adds r0, r1, #4" (1 cycle), "ldr r1, [pc, #44]" (2 cycles)
+        let trace = vec![
+            instruction(0, &[0x80, 0xb5]),
+            instruction(1, &[0x08, 0x1d]),
+            instruction(2, &[0x0b, 0x49]),
+        ];
+        let filtered = |cycle: usize, trace: &[TraceRecord]| {
+            let mut records = trace.to_vec();
+            ClockGlitch { cycle }.filter(&mut records, &cs);
+            records
+                .iter()
+                .map(|record| record.address())
+                .collect::<Vec<u64>>()
+        };
+        assert_eq!(filtered(2, &trace), [0x8000000]);
+        assert_eq!(filtered(3, &trace), [0x8000002]);
+        assert_eq!(filtered(5, &trace), [0x8000004]);
+        assert!(filtered(6, &trace).is_empty());
+        // Cycles are unknown behind the gap of a reduced trace
+        assert!(filtered(4, &[trace[0].clone(), trace[2].clone()]).is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Disassembly, FaultFunctions, FaultType};
+use super::{xor_description, Disassembly, FaultFunctions, FaultType};
 use crate::simulation::{
     cpu::Cpu,
     fault_data::FaultData,
@@ -26,6 +26,11 @@ impl CmdBitFlip {
     pub fn new(xor_value: u32) -> Arc<Self> {
         Arc::new(Self { xor_value })
     }
+
+    /// Check if all flipped bits fit into an instruction of the given size
+    fn fits(&self, instruction_size: usize) -> bool {
+        instruction_size >= 4 || self.xor_value >> (instruction_size * 8) == 0
+    }
 }
 
 impl FaultFunctions for CmdBitFlip {
@@ -33,10 +38,20 @@ impl FaultFunctions for CmdBitFlip {
     /// Return
     ///     false:  No code repair needed
     ///     true:   Code repair after fault injection is required
-    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> bool {
+    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> Result<bool, String> {
         // Get current assembler instruction
         let (address, original_instruction) = cpu.asm_cmd_read();
 
+        // Check that no bit of the following instruction is flipped
+        if !self.fits(original_instruction.len()) {
+            return Err(format!(
+                "{:?}: bit out of range for {} byte instruction at 0x{:X}",
+                self,
+                original_instruction.len(),
+                address
+            ));
+        }
+
         // Set original instructions to same as the original read instructions
         let mut modified_instruction = original_instruction.clone();
 
@@ -44,7 +59,8 @@ impl FaultFunctions for CmdBitFlip {
         for (i, byte) in &mut modified_instruction.iter_mut().enumerate() {
             *byte ^= self.xor_value.to_le_bytes()[i];
         }
-        cpu.asm_cmd_write(address, &modified_instruction).unwrap();
+        cpu.asm_cmd_write(address, &modified_instruction)
+            .map_err(|e| format!("Failed to write instruction at 0x{:X}: {:?}", address, e))?;
 
         let record = TraceRecord::Fault {
             address,
@@ -67,19 +83,33 @@ impl FaultFunctions for CmdBitFlip {
         cpu.get_trace_data().push(record.clone());
 
         // Push to fault data vector
-        cpu.get_fault_data().push(FaultData {
+        cpu.get_fault_data().push(FaultData::new(
+            record,
+            fault.clone(),
             original_instruction,
             modified_instruction,
-            record,
-            fault: fault.clone(),
-        });
+        ));
 
         // Trigger code repair after fault injection
-        true
+        Ok(true)
     }
 
     /// Filtering of traces to reduce the number of traces to analyze
-    fn filter(&self, _records: &mut Vec<TraceRecord>, _cs: &Disassembly) {}
+    ///
+    /// Instructions which are too short for the flipped bits are removed
+    fn filter(&self, records: &mut Vec<TraceRecord>, _cs: &Disassembly) {
+        records.retain(|record| match record {
+            TraceRecord::Instruction {
+                asm_instruction, ..
+            } => self.fits(asm_instruction.len()),
+            _ => false,
+        });
+    }
+
+    /// Short description of the fault for logging
+    fn description(&self) -> String {
+        format!("bitflip{}", xor_description(self.xor_value))
+    }
 
     /// Try to parse a Example fault from a string
     fn try_from(&self, input: &str) -> Option<FaultType> {
@@ -98,6 +128,12 @@ impl FaultFunctions for CmdBitFlip {
         }
         None
     }
+
+    /// Get the name of the fault type
+    fn name(&self) -> &'static str {
+        "Command BitFlip"
+    }
+
     /// Get the list of possible/good faults
     fn get_list(&self) -> Vec<String> {
         let mut list = Vec::new();
@@ -109,3 +145,17 @@ impl FaultFunctions for CmdBitFlip {
         list
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_range_of_short_instruction() {
+        // Highest bit of a 2 byte instruction
+        assert!(CmdBitFlip { xor_value: 1 << 15 }.fits(2));
+        // First bit of the following instruction
+        assert!(!CmdBitFlip { xor_value: 1 << 16 }.fits(2));
+        assert!(CmdBitFlip { xor_value: 1 << 31 }.fits(4));
+    }
+}
@@ -0,0 +1,164 @@
+use super::{Disassembly, FaultFunctions, FaultType};
+use crate::simulation::{
+    cpu::Cpu,
+    fault_data::FaultData,
+    record::{FaultRecord, TraceRecord},
+};
+use itertools::Itertools;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Command multi bit flip fault structure
+/// bits    Bit positions inside the instruction which are flipped at once
+///
+#[derive(Clone)]
+pub struct CmdMultiBitFlip {
+    pub bits: Vec<usize>,
+}
+
+impl Debug for CmdMultiBitFlip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Command MultiBitFlip (cmdmbf_{})",
+            self.bits.iter().join("_")
+        )
+    }
+}
+
+/// Implementation for CmdMultiBitFlip fault
+impl CmdMultiBitFlip {
+    /// Create a new CmdMultiBitFlip fault
+    pub fn new(bits: Vec<usize>) -> Arc<Self> {
+        Arc::new(Self { bits })
+    }
+
+    /// Check if all bit positions fit into an instruction of the given size
+    fn fits(&self, instruction_size: usize) -> bool {
+        self.bits.iter().all(|bit| *bit < instruction_size * 8)
+    }
+}
+
+impl FaultFunctions for CmdMultiBitFlip {
+    /// Flip all given bits of the command code before execution
+    /// Return
+    ///     false:  No code repair needed
+    ///     true:   Code repair after fault injection is required
+    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> Result<bool, String> {
+        // Get current assembler instruction
+        let (address, original_instruction) = cpu.asm_cmd_read();
+
+        // Check that all bit positions are located inside the instruction
+        if !self.fits(original_instruction.len()) {
+            return Err(format!(
+                "{:?}: bit position out of range for {} byte instruction at 0x{:X}",
+                self,
+                original_instruction.len(),
+                address
+            ));
+        }
+
+        // Flip all bits of the instruction
+        let mut modified_instruction = original_instruction.clone();
+        for bit in &self.bits {
+            modified_instruction[bit / 8] ^= 1 << (bit % 8);
+        }
+        cpu.asm_cmd_write(address, &modified_instruction)
+            .map_err(|e| format!("Failed to write instruction at 0x{:X}: {:?}", address, e))?;
+
+        let record = TraceRecord::Fault {
+            address,
+            fault_type: format!(
+                "Command MultiBitFlip (cmdmbf_{}) 0x{:x} -> 0x{:x}",
+                self.bits.iter().join("_"),
+                original_instruction
+                    .iter()
+                    .enumerate()
+                    .map(|(i, b)| (*b as u32) << (i * 8) as u32)
+                    .sum::<u32>(),
+                modified_instruction
+                    .iter()
+                    .enumerate()
+                    .map(|(i, b)| (*b as u32) << (i * 8) as u32)
+                    .sum::<u32>()
+            ),
+            data: original_instruction.clone(),
+        };
+        cpu.get_trace_data().push(record.clone());
+
+        // Push to fault data vector
+        cpu.get_fault_data().push(FaultData::new(
+            record,
+            fault.clone(),
+            original_instruction,
+            modified_instruction,
+        ));
+
+        // Trigger code repair after fault injection
+        Ok(true)
+    }
+
+    /// Filtering of traces to reduce the number of traces to analyze
+    ///
+    /// Instructions which are too short for the given bit positions are removed
+    fn filter(&self, records: &mut Vec<TraceRecord>, _cs: &Disassembly) {
+        records.retain(|record| match record {
+            TraceRecord::Instruction {
+                asm_instruction, ..
+            } => self.fits(asm_instruction.len()),
+            _ => false,
+        });
+    }
+
+    /// Short description of the fault for logging
+    fn description(&self) -> String {
+        format!("bitflip@bit{}", self.bits.iter().join(","))
+    }
+
+    /// Try to parse a CmdMultiBitFlip fault from a string
+    fn try_from(&self, input: &str) -> Option<FaultType> {
+        // divide name from attributes
+        let collect: Vec<&str> = input.split('_').collect();
+        // check if name and at least one attribute are present
+        let fault_type = collect.first().copied()?;
+        if fault_type == "cmdmbf" && collect.len() > 1 {
+            // check if all attributes are valid bit positions
+            let bits: Result<Vec<usize>, _> = collect[1..]
+                .iter()
+                .map(|bit| bit.parse::<usize>())
+                .collect();
+            if let Ok(bits) = bits {
+                // return CmdMultiBitFlip struct
+                return Some(Self::new(bits));
+            }
+        }
+        None
+    }
+
+    /// Get the name of the fault type
+    fn name(&self) -> &'static str {
+        "Command MultiBitFlip"
+    }
+
+    /// Get the list of possible/good faults
+    fn get_list(&self) -> Vec<String> {
+        let mut list = Vec::new();
+        // Generate a list of adjacent double bit flips
+        // Values will look like: cmdmbf_0_1, cmdmbf_1_2, ...
+        for index in 0..31 {
+            list.push(format!("cmdmbf_{}_{}", index, index + 1));
+        }
+        // Generate a list of nibble flips
+        // Values will look like: cmdmbf_0_1_2_3, cmdmbf_4_5_6_7, ...
+        for index in (0..32).step_by(4) {
+            list.push(format!(
+                "cmdmbf_{}_{}_{}_{}",
+                index,
+                index + 1,
+                index + 2,
+                index + 3
+            ));
+        }
+        list
+    }
+}
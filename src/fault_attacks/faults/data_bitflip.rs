@@ -0,0 +1,138 @@
+use super::{xor_description, Disassembly, FaultFunctions, FaultType};
+use crate::simulation::{
+    cpu::{Cpu, Endianness},
+    fault_data::FaultData,
+    record::{FaultRecord, TraceRecord},
+};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Data bit flip fault structure
+/// address     Address of the 32 bit data word in memory
+/// xor_value   Bits to flip in the data word
+///
+/// The bits are flipped in the current data word when it is read the next time
+/// after injection
+#[derive(Clone, Copy)]
+pub struct DataBitFlip {
+    pub address: u64,
+    pub xor_value: u32,
+}
+
+impl Debug for DataBitFlip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Data BitFlip (databf_{:08x}_{:08x})",
+            self.address, self.xor_value
+        )
+    }
+}
+
+/// Implementation for DataBitFlip fault
+impl DataBitFlip {
+    /// Create a new DataBitFlip fault
+    pub fn new(address: u64, xor_value: u32) -> Arc<Self> {
+        Arc::new(Self { address, xor_value })
+    }
+}
+
+impl FaultFunctions for DataBitFlip {
+    /// Setup a read hook which flips the bits of the data word on the next read access
+    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> Result<bool, String> {
+        let address = cpu.get_program_counter();
+
+        // Read current data value
+        let mut original_data = [0; 4];
+        cpu.memory_read(self.address, &mut original_data)
+            .map_err(|e| format!("Failed to read data at 0x{:X}: {:?}", self.address, e))?;
+        let (original_value, xor_mask) = match cpu.get_endianness() {
+            Endianness::Little => (
+                u32::from_le_bytes(original_data),
+                self.xor_value.to_le_bytes(),
+            ),
+            Endianness::Big => (
+                u32::from_be_bytes(original_data),
+                self.xor_value.to_be_bytes(),
+            ),
+        };
+        cpu.set_data_fault_hook(self.address, xor_mask.to_vec())
+            .map_err(|e| format!("Failed to setup data fault hook: {:?}", e))?;
+
+        // Read assembler line
+        let cmd_size = cpu
+            .get_asm_cmd_size(address)
+            .map_err(|e| format!("Failed to read instruction at 0x{:X}: {:?}", address, e))?;
+        let mut original_instructions = vec![0; cmd_size];
+        // Read original instructions
+        cpu.memory_read(address, &mut original_instructions)
+            .map_err(|e| format!("Failed to read instruction at 0x{:X}: {:?}", address, e))?;
+
+        let record = TraceRecord::Fault {
+            address,
+            fault_type: format!(
+                "Data BitFlip (Addr: 0x{:08x}, Value: {:08x}) 0x{:08x} -> 0x{:08x}",
+                self.address,
+                self.xor_value,
+                original_value,
+                original_value ^ self.xor_value
+            ),
+            data: vec![],
+        };
+        cpu.get_trace_data().push(record.clone());
+
+        // Push to fault data vector
+        cpu.get_fault_data().push(FaultData {
+            original_register_value: Some(original_value as u64),
+            ..FaultData::new(record, fault.clone(), original_instructions, vec![])
+        });
+
+        // No code repair required
+        Ok(false)
+    }
+
+    /// Filtering of traces to reduce the number of traces to analyze
+    fn filter(&self, _records: &mut Vec<TraceRecord>, _cs: &Disassembly) {}
+
+    /// Short description of the fault for logging
+    fn description(&self) -> String {
+        format!(
+            "data bitflip 0x{:08x}{}",
+            self.address,
+            xor_description(self.xor_value)
+        )
+    }
+
+    /// Try to parse a DataBitFlip fault from a string
+    fn try_from(&self, input: &str) -> Option<FaultType> {
+        // divide name from attribute
+        let collect: Vec<&str> = input.split('_').collect();
+        // check if name and attributes are present
+        let fault_type = collect.first().copied()?;
+        let attribute_1 = collect.get(1).copied()?;
+        let attribute_2 = collect.get(2).copied()?;
+        // check if fault type is data bitflip
+        if fault_type == "databf" {
+            // check if attributes are valid address and value
+            if let (Ok(address), Ok(xor_value)) = (
+                u64::from_str_radix(attribute_1, 16),
+                u32::from_str_radix(attribute_2, 16),
+            ) {
+                return Some(Self::new(address, xor_value));
+            }
+        }
+        None
+    }
+
+    /// Get the name of the fault type
+    fn name(&self) -> &'static str {
+        "Data BitFlip"
+    }
+
+    /// Get the list of possible/good faults
+    ///
+    /// Data addresses depend on the program, so no faults are suggested
+    fn get_list(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
@@ -28,9 +28,9 @@ impl Example {
 
 impl FaultFunctions for Example {
     /// Execute a Example skipping `n` instructions.
-    fn execute(&self, _cpu: &mut Cpu, _fault: &FaultRecord) -> bool {
+    fn execute(&self, _cpu: &mut Cpu, _fault: &FaultRecord) -> Result<bool, String> {
         // No cleanup required
-        false
+        Ok(false)
     }
 
     /// Filtering of traces to reduce the number of traces to analyze
@@ -40,6 +40,12 @@ impl FaultFunctions for Example {
     fn try_from(&self, _input: &str) -> Option<FaultType> {
         None
     }
+
+    /// Get the name of the fault type
+    fn name(&self) -> &'static str {
+        "Example"
+    }
+
     /// Get the list of possible/good faults
     fn get_list(&self) -> Vec<String> {
         vec![]
@@ -7,8 +7,6 @@ use crate::simulation::{
 use std::fmt::Debug;
 use std::sync::Arc;
 
-const T1_NOP: [u8; 4] = [0x00, 0xBF, 0x00, 0xBF];
-
 /// Glitch fault structure
 /// number  Number of assembler instructions to advance program counter to simulate
 ///         glitching on internal cpu state machine
@@ -34,14 +32,24 @@ impl Glitch {
 
 impl FaultFunctions for Glitch {
     /// Execute a glitch skipping `n` instructions.
-    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> bool {
+    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> Result<bool, String> {
         let address = cpu.get_program_counter();
         let mut offset = 0;
         let mut modified_instructions = Vec::new();
+        let mut first_size = 0;
 
         for _count in 0..self.number {
-            let instruction_size = cpu.get_asm_cmd_size(address + offset).unwrap();
-            modified_instructions.extend_from_slice(&T1_NOP[..instruction_size]);
+            let instruction_size = cpu.get_asm_cmd_size(address + offset).map_err(|e| {
+                format!(
+                    "Failed to read instruction at 0x{:X}: {:?}",
+                    address + offset,
+                    e
+                )
+            })?;
+            if offset == 0 {
+                first_size = instruction_size;
+            }
+            modified_instructions.extend(cpu.nop_cmd(instruction_size)?);
             offset += instruction_size as u64;
         }
         cpu.set_program_counter(address + offset);
@@ -61,19 +69,27 @@ impl FaultFunctions for Glitch {
 
         // Push to fault data vector
         cpu.get_fault_data().push(FaultData {
-            original_instruction: original_instructions,
-            modified_instruction: modified_instructions,
-            record,
-            fault: fault.clone(),
+            instruction_size: first_size,
+            ..FaultData::new(
+                record,
+                fault.clone(),
+                original_instructions,
+                modified_instructions,
+            )
         });
 
         // No cleanup required
-        false
+        Ok(false)
     }
 
     /// Filtering of traces to reduce the number of traces to analyze
     fn filter(&self, _records: &mut Vec<TraceRecord>, _cs: &Disassembly) {}
 
+    /// Short description of the fault for logging
+    fn description(&self) -> String {
+        format!("NOP x{}", self.number)
+    }
+
     /// Try to parse a Glitch fault from a string
     fn try_from(&self, input: &str) -> Option<FaultType> {
         // divide name from attribute
@@ -91,9 +107,52 @@ impl FaultFunctions for Glitch {
         }
         None
     }
+
+    /// Get the name of the fault type
+    fn name(&self) -> &'static str {
+        "Glitch"
+    }
+
     /// Get the list of possible/good faults
     fn get_list(&self) -> Vec<String> {
         let list: Vec<String> = (1..=8).map(|index| format!("glitch_{}", index)).collect();
         list
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_file::ElfFile;
+    use crate::simulation::config::SimulationConfig;
+
+    #[test]
+    fn glitch_mixed_instruction_sizes() {
+        let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+        let config = SimulationConfig::default();
+        let mut cpu = Cpu::new(&file_data, &config).unwrap();
+        cpu.setup_mmio().unwrap();
+        cpu.load_code();
+
+        // Glitch over "ldr r1, [pc, #44]", "adds r0, r1, #4" and "ldr r2, [r1], #28" in main
+        cpu.set_program_counter(0x8000630);
+        let glitch = Glitch::new(3);
+        let fault = FaultRecord {
+            index: 0,
+            fault_type: glitch.clone(),
+        };
+        assert!(!glitch.execute(&mut cpu, &fault).unwrap());
+
+        // All three instructions (2 + 2 + 4 bytes) are skipped
+        assert_eq!(cpu.get_program_counter(), 0x8000638);
+        let fault_data = &cpu.get_fault_data()[0];
+        assert_eq!(
+            fault_data.original_instruction,
+            [0x0b, 0x49, 0x08, 0x1d, 0x51, 0xf8, 0x1c, 0x2b]
+        );
+        assert_eq!(
+            fault_data.modified_instruction,
+            [0x00, 0xbf, 0x00, 0xbf, 0x00, 0xbf, 0x00, 0xbf]
+        );
+    }
+}
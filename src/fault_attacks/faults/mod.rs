@@ -1,22 +1,40 @@
 use super::{FaultRecord, TraceRecord};
 use crate::{disassembly::Disassembly, simulation::cpu::Cpu};
-use std::{fmt::Debug, slice::Iter, sync::Arc};
+use std::{
+    fmt::{self, Debug},
+    slice::Iter,
+    sync::Arc,
+};
 
+pub mod branch_flip;
+pub mod byte_mask;
+pub mod byte_set;
+pub mod clock_glitch;
 pub mod cmd_bitflip;
+pub mod cmd_multi_bitflip;
+pub mod data_bitflip;
 pub mod glitch;
 pub mod register_bitflip;
+pub mod register_fault;
 pub mod register_flood;
 
+pub use branch_flip::BranchFlip;
+pub use byte_mask::ByteMask;
+pub use byte_set::ByteSet;
+pub use clock_glitch::ClockGlitch;
 pub use cmd_bitflip::CmdBitFlip;
+pub use cmd_multi_bitflip::CmdMultiBitFlip;
+pub use data_bitflip::DataBitFlip;
 pub use glitch::Glitch;
 use itertools::Itertools;
 pub use register_bitflip::RegisterBitFlip;
+pub use register_fault::{RegOp, RegisterFault};
 pub use register_flood::RegisterFlood;
 
 use unicorn_engine::RegisterARM;
 
 /// List of all possible faults
-const FAULTS: [&dyn FaultFunctions; 4] = [
+const FAULTS: [&dyn FaultFunctions; 11] = [
     &Glitch { number: 1 },
     &RegisterBitFlip {
         register: RegisterARM::R0,
@@ -27,14 +45,63 @@ const FAULTS: [&dyn FaultFunctions; 4] = [
         value: 0x00,
     },
     &CmdBitFlip { xor_value: 0x01 },
+    &ByteSet {
+        offset: 0,
+        value: 0x00,
+        len: 2,
+    },
+    &CmdMultiBitFlip { bits: Vec::new() },
+    &RegisterFault {
+        register: RegisterARM::R0,
+        mask: 0x00,
+        op: RegOp::Xor,
+    },
+    &DataBitFlip {
+        address: 0,
+        xor_value: 0x01,
+    },
+    &BranchFlip,
+    &ByteMask {
+        offset: 0,
+        and_mask: 0xff,
+        or_mask: 0x00,
+    },
+    &ClockGlitch { cycle: 0 },
 ];
 
 /// Trait for fault injection functions
+///
+/// Custom faults implement this trait and are used with
+/// `FaultRecord { index, fault_type: Arc::new(custom_fault) }`. `execute` is called
+/// at the fault address, modifies the cpu and pushes its `FaultData`. Returning
+/// true restores the original instruction after it was executed once
 pub trait FaultFunctions: Send + Sync + Debug {
-    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> bool;
+    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> Result<bool, String>;
     fn filter(&self, records: &mut Vec<TraceRecord>, cs: &Disassembly);
     fn try_from(&self, input: &str) -> Option<FaultType>;
     fn get_list(&self) -> Vec<String>;
+
+    /// Name of the fault type without its parameters (e.g. "Glitch")
+    fn name(&self) -> &'static str;
+
+    /// Short description of the fault for logging (e.g. "NOP x3")
+    fn description(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+impl fmt::Display for dyn FaultFunctions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Describe a xor mask as "@bit<n>" for single bits and "^0x<mask>" otherwise
+fn xor_description(xor_value: u32) -> String {
+    match xor_value.count_ones() {
+        1 => format!("@bit{}", xor_value.trailing_zeros()),
+        _ => format!("^0x{:08x}", xor_value),
+    }
 }
 
 /// Type definition of fault injection data type
@@ -60,8 +127,10 @@ pub fn get_fault_lists(groups: &mut Iter<String>) -> Vec<Vec<String>> {
             .filter(|fault| {
                 // Get the first item of the list, get the attack name and check if it is in the list
                 let list = fault.get_list();
-                let item = list[0].split('_').collect::<Vec<&str>>();
-                fault_types.contains(item[0])
+                match list.first() {
+                    Some(item) => fault_types.contains(item.split('_').next().unwrap()),
+                    None => false,
+                }
             })
             .map(|fault| fault.get_list())
             .collect()
@@ -1,4 +1,4 @@
-use super::{Disassembly, FaultFunctions, FaultType};
+use super::{xor_description, Disassembly, FaultFunctions, FaultType};
 use crate::simulation::{
     cpu::{Cpu, ARM_REG},
     fault_data::FaultData,
@@ -40,7 +40,7 @@ impl RegisterBitFlip {
 
 impl FaultFunctions for RegisterBitFlip {
     /// Execute a bit flip in the given register.
-    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> bool {
+    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> Result<bool, String> {
         let address = cpu.get_program_counter();
 
         // Read and write changed register
@@ -49,7 +49,10 @@ impl FaultFunctions for RegisterBitFlip {
             .unwrap();
 
         // Read assembler line
-        let mut original_instructions = vec![0; cpu.get_asm_cmd_size(address).unwrap()];
+        let cmd_size = cpu
+            .get_asm_cmd_size(address)
+            .map_err(|e| format!("Failed to read instruction at 0x{:X}: {:?}", address, e))?;
+        let mut original_instructions = vec![0; cmd_size];
         // Read original instructions
         cpu.memory_read(address, &mut original_instructions)
             .unwrap();
@@ -69,14 +72,12 @@ impl FaultFunctions for RegisterBitFlip {
 
         // Push to fault data vector
         cpu.get_fault_data().push(FaultData {
-            original_instruction: original_instructions,
-            modified_instruction: vec![],
-            record,
-            fault: fault.clone(),
+            original_register_value: Some(reg_val),
+            ..FaultData::new(record, fault.clone(), original_instructions, vec![])
         });
 
         // No cleanup required
-        false
+        Ok(false)
     }
 
     /// Filtering of traces to reduce the number of traces to analyze
@@ -95,6 +96,15 @@ impl FaultFunctions for RegisterBitFlip {
         });
     }
 
+    /// Short description of the fault for logging
+    fn description(&self) -> String {
+        format!(
+            "R{} bitflip{}",
+            self.register as u32 - RegisterARM::R0 as u32,
+            xor_description(self.xor_value)
+        )
+    }
+
     /// Try to parse a Glitch fault from a string
     fn try_from(&self, input: &str) -> Option<FaultType> {
         // divide name from attribute
@@ -119,6 +129,12 @@ impl FaultFunctions for RegisterBitFlip {
         }
         None
     }
+
+    /// Get the name of the fault type
+    fn name(&self) -> &'static str {
+        "Register BitFlip"
+    }
+
     /// Get the list of possible/good faults
     fn get_list(&self) -> Vec<String> {
         let mut list = Vec::new();
@@ -0,0 +1,195 @@
+use super::{Disassembly, FaultFunctions, FaultType};
+use crate::simulation::{
+    cpu::{Cpu, ARM_REG},
+    fault_data::FaultData,
+    record::{FaultRecord, TraceRecord},
+};
+use std::fmt::Debug;
+use std::sync::Arc;
+use unicorn_engine::RegisterARM;
+
+/// Operation which is applied with the mask to the register value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegOp {
+    /// Set all mask bits
+    Set,
+    /// Clear all mask bits
+    Clear,
+    /// Flip all mask bits
+    Xor,
+}
+
+impl RegOp {
+    /// Apply operation with the mask to the value
+    pub fn apply(&self, value: u32, mask: u32) -> u32 {
+        match self {
+            RegOp::Set => value | mask,
+            RegOp::Clear => value & !mask,
+            RegOp::Xor => value ^ mask,
+        }
+    }
+
+    /// Short name used in the fault string
+    fn name(&self) -> &'static str {
+        match self {
+            RegOp::Set => "set",
+            RegOp::Clear => "clr",
+            RegOp::Xor => "xor",
+        }
+    }
+
+    /// Parse operation from the short name
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "set" => Some(RegOp::Set),
+            "clr" => Some(RegOp::Clear),
+            "xor" => Some(RegOp::Xor),
+            _ => None,
+        }
+    }
+}
+
+/// Register fault structure
+/// register    Register which is corrupted
+/// mask        Bits which are changed
+/// op          Operation applied with the mask
+///
+#[derive(Clone, Copy)]
+pub struct RegisterFault {
+    pub register: RegisterARM,
+    pub mask: u32,
+    pub op: RegOp,
+}
+
+impl Debug for RegisterFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Register Fault (regop_r{}_{}_{:08x})",
+            self.register as u32 - RegisterARM::R0 as u32,
+            self.op.name(),
+            self.mask
+        )
+    }
+}
+
+/// Implementation for RegisterFault fault
+impl RegisterFault {
+    /// Create a new RegisterFault fault
+    pub fn new(register: RegisterARM, mask: u32, op: RegOp) -> Arc<Self> {
+        Arc::new(Self { register, mask, op })
+    }
+}
+
+impl FaultFunctions for RegisterFault {
+    /// Execute the register operation on the given register.
+    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> Result<bool, String> {
+        let address = cpu.get_program_counter();
+
+        // Read and write changed register
+        let reg_val = cpu.register_read(self.register).unwrap();
+        let new_val = self.op.apply(reg_val as u32, self.mask);
+        cpu.register_write(self.register, new_val as u64).unwrap();
+
+        // Read assembler line
+        let cmd_size = cpu
+            .get_asm_cmd_size(address)
+            .map_err(|e| format!("Failed to read instruction at 0x{:X}: {:?}", address, e))?;
+        let mut original_instructions = vec![0; cmd_size];
+        // Read original instructions
+        cpu.memory_read(address, &mut original_instructions)
+            .unwrap();
+
+        let record = TraceRecord::Fault {
+            address,
+            fault_type: format!(
+                "Register Fault (Reg: R{}, Op: {}, Mask: {:08x}) 0x{:08x} -> 0x{:08x}",
+                self.register as u32 - RegisterARM::R0 as u32,
+                self.op.name(),
+                self.mask,
+                reg_val,
+                new_val
+            ),
+            data: vec![],
+        };
+        cpu.get_trace_data().push(record.clone());
+
+        // Push to fault data vector
+        cpu.get_fault_data().push(FaultData {
+            original_register_value: Some(reg_val),
+            ..FaultData::new(record, fault.clone(), original_instructions, vec![])
+        });
+
+        // No cleanup required
+        Ok(false)
+    }
+
+    /// Filtering of traces to reduce the number of traces to analyze
+    fn filter(&self, records: &mut Vec<TraceRecord>, cs: &Disassembly) {
+        records.retain(|record| match record {
+            TraceRecord::Instruction {
+                address,
+                asm_instruction,
+                ..
+            } => cs.check_for_register(
+                asm_instruction,
+                *address,
+                self.register as u32 - RegisterARM::R0 as u32,
+            ),
+            _ => false,
+        });
+    }
+
+    /// Short description of the fault for logging
+    fn description(&self) -> String {
+        format!(
+            "R{} {} 0x{:08x}",
+            self.register as u32 - RegisterARM::R0 as u32,
+            self.op.name(),
+            self.mask
+        )
+    }
+
+    /// Try to parse a RegisterFault fault from a string
+    fn try_from(&self, input: &str) -> Option<FaultType> {
+        // divide name from attribute
+        let collect: Vec<&str> = input.split('_').collect();
+        // check if name and attributes are present
+        let fault_type = collect.first().copied()?;
+        let attribute_1 = collect.get(1).copied()?;
+        let attribute_2 = collect.get(2).copied()?;
+        let attribute_3 = collect.get(3).copied()?;
+        // check if fault type is register fault
+        if fault_type == "regop" {
+            // check if attribute is a valid register
+            let register = attribute_1.strip_prefix('r')?.parse::<usize>().ok()?;
+            let register = ARM_REG.get(register).copied()?;
+            // check if attributes are a valid operation and mask
+            let op = RegOp::from_name(attribute_2)?;
+            let mask = u32::from_str_radix(attribute_3, 16).ok()?;
+            // return RegisterFault struct
+            return Some(Self::new(register, mask, op));
+        }
+        None
+    }
+
+    /// Get the name of the fault type
+    fn name(&self) -> &'static str {
+        "Register Fault"
+    }
+
+    /// Get the list of possible/good faults
+    fn get_list(&self) -> Vec<String> {
+        let mut list = Vec::new();
+        // Generate a list of half word stuck-at faults
+        // Values will look like: regop_r0_clr_0000ffff, regop_r0_set_ffff0000, ...
+        for reg in 0..=12 {
+            for op in ["clr", "set"] {
+                for mask in [0x0000ffffu32, 0xffff0000] {
+                    list.push(format!("regop_r{}_{}_{:08x}", reg, op, mask));
+                }
+            }
+        }
+        list
+    }
+}
@@ -1,45 +1,231 @@
 pub mod faults;
+pub mod statistics;
 
 use super::simulation::{
+    config::{SimulationBuilder, SimulationConfig},
+    cpu::{clear_thumb, Endianness, MemoryLayout, RunState, TargetArch},
     fault_data::FaultData,
     record::{FaultRecord, TraceRecord},
     Control, Data, RunType,
 };
+#[cfg(feature = "serde")]
+use crate::simulation::serialize::Checkpoint;
 use crate::{disassembly::Disassembly, elf_file::ElfFile};
 use faults::*;
 use indicatif::ProgressBar;
-use itertools::iproduct;
+use itertools::{iproduct, Itertools};
 use log::debug;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+use statistics::CampaignComparison;
 use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::Write,
+    path::Path,
     slice::Iter,
-    sync::mpsc::{channel, Sender},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{channel, Sender},
+        Arc,
+    },
+    time::Duration,
 };
 
+/// Effect of a fault attack on the program
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultEffect {
+    /// Successful attack
+    Exploit,
+    /// Program flow is identical to the nominal run
+    Ineffective,
+    /// Program flow changed, but the attack was not successful
+    Changed,
+    /// Simulation stopped with an error (e.g. crash)
+    Error,
+}
+
+/// Fault located by symbol and instruction index instead of the program flow
+///
+/// The location stays the same between builds of a program, see
+/// `FaultAttacks::compare_campaigns`
+#[derive(Clone, Debug)]
+pub struct SymbolFault {
+    pub symbol: String,
+    /// Instruction index from the start of the symbol (0 = first instruction)
+    pub offset: usize,
+    pub fault_type: FaultType,
+}
+
+/// Fault of an attack file, see `FaultAttacks::load_attacks`
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AttackFileFault {
+    fault_type: String,
+    address: Option<AttackFileAddress>,
+    symbol: Option<String>,
+    /// Instruction index from the start of the symbol
+    #[serde(default)]
+    offset: usize,
+    /// Number of successive executions of the location which are faulted
+    count: usize,
+    /// Execution of the location with the first fault (default: 1)
+    trigger_count: Option<usize>,
+}
+
+/// Address of an attack file as number or string (hex with "0x" prefix)
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AttackFileAddress {
+    Number(u64),
+    Text(String),
+}
+
+/// Progress callback of a fault campaign: (current, total)
+pub type ProgressFn = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
+/// Result of an attack of a campaign: fault data if successful and emulation time
+type AttackResult = (Option<Vec<FaultData>>, Duration);
+
 pub struct FaultAttacks {
     cs: Disassembly,
     pub file_data: ElfFile,
+    pub config: SimulationConfig,
     pub fault_data: Vec<Vec<FaultData>>,
     pub count_sum: usize,
+    progress: Option<ProgressFn>,
+    success_count: Arc<AtomicUsize>,
+    cancel: Arc<AtomicBool>,
 }
 
 impl FaultAttacks {
     pub fn new(path: std::path::PathBuf) -> Result<Self, String> {
+        Self::with_layout(path, MemoryLayout::default())
+    }
+
+    /// Create a new fault attack instance with a custom memory layout
+    ///
+    /// Use this for targets which are linked with a different linker script
+    pub fn with_layout(
+        path: std::path::PathBuf,
+        memory_layout: MemoryLayout,
+    ) -> Result<Self, String> {
+        // Load victim data
+        let file_data: ElfFile = ElfFile::new(path)?;
+        // Detect target architecture and byte order from elf header
+        let config = SimulationConfig {
+            memory_layout,
+            target_arch: TargetArch::from_elf(&file_data),
+            endianness: Endianness::from_elf(&file_data),
+            ..Default::default()
+        };
+
+        Ok(Self {
+            cs: Disassembly::with_target(config.target_arch, config.endianness),
+            file_data,
+            config,
+            fault_data: Vec::new(),
+            count_sum: 0,
+            progress: None,
+            success_count: Arc::new(AtomicUsize::new(0)),
+            cancel: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Create a new fault attack instance with a custom simulation configuration
+    ///
+    /// The target architecture of the configuration is used as given
+    pub fn with_config(path: std::path::PathBuf, config: SimulationConfig) -> Result<Self, String> {
         // Load victim data
         let file_data: ElfFile = ElfFile::new(path)?;
 
         Ok(Self {
-            cs: Disassembly::new(),
+            cs: Disassembly::with_target(config.target_arch, config.endianness),
             file_data,
+            config,
             fault_data: Vec::new(),
             count_sum: 0,
+            progress: None,
+            success_count: Arc::new(AtomicUsize::new(0)),
+            cancel: Arc::new(AtomicBool::new(false)),
         })
     }
 
+    /// Set a callback which is called with (current, total) after each attack
+    ///
+    /// The callback is called from the worker threads of the campaign
+    pub fn set_progress_callback<F>(&mut self, progress: F)
+    where
+        F: Fn(usize, usize) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(progress));
+    }
+
+    /// Get the running number of successful attacks
+    ///
+    /// The counter is reset at the start of each campaign and can be read inside the
+    /// progress callback, e.g. to stop a campaign when enough successful attacks are found
+    pub fn success_counter(&self) -> Arc<AtomicUsize> {
+        self.success_count.clone()
+    }
+
+    /// Get the flag to cancel the running campaign
+    ///
+    /// After the flag is set all remaining attacks are skipped, attacks which are
+    /// already running are completed. The flag is cleared at the start of each campaign
+    pub fn cancel_flag(&self) -> Arc<AtomicBool> {
+        self.cancel.clone()
+    }
+
+    /// Reset the success counter and the cancel flag for a new campaign
+    fn start_campaign(&self) {
+        self.success_count.store(0, Ordering::Relaxed);
+        self.cancel.store(false, Ordering::Relaxed);
+    }
+
+    /// Replace the simulation configuration
+    pub fn set_config(&mut self, config: SimulationConfig) {
+        self.cs = Disassembly::with_target(config.target_arch, config.endianness);
+        self.config = config;
+    }
+
+    /// Add an address which must not be executed
+    ///
+    /// A run reaching the address is stopped and classified as successful attack
+    /// with the stop reason `GuardViolation`
+    pub fn add_guard_address(&mut self, address: u64) {
+        self.config.guard_addresses.push(address);
+    }
+
+    /// Add a handler for a custom MMIO peripheral to the simulation configuration
+    pub fn add_mmio_handler<R, W>(&mut self, base: u64, size: usize, read_fn: R, write_fn: W)
+    where
+        R: Fn(u64, usize) -> u64 + Send + Sync + 'static,
+        W: Fn(u64, usize, u64) + Send + Sync + 'static,
+    {
+        self.config.add_mmio_handler(base, size, read_fn, write_fn);
+    }
+
     pub fn set_fault_data(&mut self, fault_data: Vec<Vec<FaultData>>) {
         self.fault_data = fault_data;
     }
 
+    /// Export all successful attacks with their fault data to a JSON file
+    #[cfg(feature = "serde")]
+    pub fn export_faults_json(&self, path: &std::path::Path) -> Result<(), String> {
+        let file = std::fs::File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let export = crate::simulation::serialize::FaultExport {
+            seed: self.config.seed,
+            attacks: &self.fault_data,
+        };
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), &export)
+            .map_err(|e| format!("Failed to write fault data: {}", e))
+    }
+
     pub fn print_fault_data(&self) {
         let debug_context = self.file_data.get_debug_context();
 
@@ -55,6 +241,7 @@ impl FaultAttacks {
             // Run full trace
             let trace_records = Some(trace_run(
                 &self.file_data,
+                &self.config,
                 cycles,
                 RunType::RecordFullTrace,
                 true,
@@ -75,6 +262,7 @@ impl FaultAttacks {
         // Run full trace
         let trace_records = Some(trace_run(
             &self.file_data,
+            &self.config,
             cycles,
             RunType::RecordFullTrace,
             true,
@@ -89,10 +277,921 @@ impl FaultAttacks {
         Ok(())
     }
 
+    /// Record the executed instructions in execution order
+    ///
+    /// Returns (address, instruction size) for every executed instruction including
+    /// loop iterations. The trace is bounded by the given instruction limit
+    pub fn record_ordered_trace(&self, cycles: usize) -> Result<Vec<(u64, usize)>, String> {
+        let trace_records = trace_run(
+            &self.file_data,
+            &self.config,
+            cycles,
+            RunType::RecordTrace,
+            true,
+            &[],
+        )?;
+
+        Ok(trace_records
+            .iter()
+            .filter_map(|record| match record {
+                TraceRecord::Instruction {
+                    address,
+                    asm_instruction,
+                    ..
+                } => Some((*address, asm_instruction.len())),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Check that the nominal run is deterministic
+    ///
+    /// The program flow is recorded `runs` times and compared with the first run.
+    /// Returns the first address where the program flow differs, or None if all
+    /// runs are identical (e.g. a MMIO handler returning random values is detected)
+    pub fn verify_determinism(&self, cycles: usize, runs: usize) -> Result<Option<u64>, String> {
+        let golden = self.record_ordered_trace(cycles)?;
+        for _ in 1..runs {
+            let trace = self.record_ordered_trace(cycles)?;
+            if trace != golden {
+                // Get first diverging position (or end of the shorter trace)
+                let position = golden
+                    .iter()
+                    .zip(trace.iter())
+                    .position(|(golden, trace)| golden != trace)
+                    .unwrap_or(golden.len().min(trace.len()));
+                let address = match trace.get(position).or(golden.get(position)) {
+                    Some((address, _)) => *address,
+                    None => 0,
+                };
+                return Ok(Some(address));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Export the executed instructions of the nominal run to a CSV file
+    ///
+    /// Every executed address is listed once with its instruction size and
+    /// execution count, sorted by address
+    pub fn export_trace_csv(&self, cycles: usize, path: &Path) -> Result<(), String> {
+        let trace = self.record_ordered_trace(cycles)?;
+        let file = std::fs::File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        write_trace_csv(std::io::BufWriter::new(file), &trace)
+            .map_err(|e| format!("Failed to write trace data: {}", e))
+    }
+
+    /// Get executed addresses of the nominal run annotated with their function
+    ///
+    /// Returns (address, function + offset, count) sorted by address
+    pub fn annotated_trace(&self, cycles: usize) -> Result<Vec<(u64, String, usize)>, String> {
+        let mut counts: BTreeMap<u64, usize> = BTreeMap::new();
+        for (address, _) in self.record_ordered_trace(cycles)? {
+            *counts.entry(address).or_default() += 1;
+        }
+
+        Ok(counts
+            .into_iter()
+            .map(|(address, count)| {
+                let function = self.file_data.function_at(address).unwrap_or_default();
+                (address, function, count)
+            })
+            .collect())
+    }
+
+    /// Compare the executed addresses of the nominal run with a faulted run
+    ///
+    /// Returns (addresses only hit in the nominal run, addresses only hit in the faulted run)
+    pub fn coverage_diff(
+        &self,
+        cycles: usize,
+        faults: &[FaultRecord],
+    ) -> Result<(HashSet<u64>, HashSet<u64>), String> {
+        let nominal = self.coverage(cycles, &[])?;
+        let faulted = self.coverage(cycles, faults)?;
+
+        Ok((
+            nominal.difference(&faulted).copied().collect(),
+            faulted.difference(&nominal).copied().collect(),
+        ))
+    }
+
+    /// Get all executed addresses of a run with the given faults
+    fn coverage(&self, cycles: usize, faults: &[FaultRecord]) -> Result<HashSet<u64>, String> {
+        let trace_records = trace_run(
+            &self.file_data,
+            &self.config,
+            cycles,
+            RunType::RecordFullTrace,
+            false,
+            faults,
+        )?;
+
+        Ok(trace_records
+            .iter()
+            .filter_map(|record| match record {
+                TraceRecord::Instruction { address, .. } => Some(*address),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Get instruction limit of runs with faults
+    ///
+    /// Without `fault_instruction_limit` in the configuration the limit is 1.5 times
+    /// the length of the nominal run, but not more than `cycles`
+    fn fault_cycles(&self, cycles: usize) -> Result<usize, String> {
+        if let Some(limit) = self.config.fault_instruction_limit {
+            return Ok(limit);
+        }
+        let config = SimulationConfig {
+            count_instructions: true,
+            ..self.config.clone()
+        };
+        let mut simulation = new_simulation(&self.file_data, &config)?;
+        simulation.set_instruction_limit(cycles);
+        simulation.run_with_faults(RunType::Run, false, &[])?;
+        let nominal = simulation.get_instruction_count();
+        Ok((nominal + nominal / 2).clamp(1, cycles.max(1)))
+    }
+
     pub fn check_for_correct_behavior(&self, cycles: usize) -> Result<(), String> {
         // Get trace data from negative run
-        let mut simulation = Control::new(&self.file_data);
-        simulation.check_program(cycles)
+        let mut simulation = Control::new(&self.file_data, &self.config)
+            .map_err(|e| format!("Simulation setup failed: {:?}", e))?;
+        simulation.set_instruction_limit(cycles);
+        simulation.check_program()
+    }
+
+    /// List all candidate fault locations of the nominal program flow without executing faults
+    ///
+    /// One fault record is created for every unique executed instruction which passes the
+    /// filter of the fault type. Each record can be used as a single fault attack
+    /// (e.g. with `run_faults_parallel`)
+    pub fn enumerate_fault_candidates(
+        &self,
+        cycles: usize,
+        fault_type: &FaultType,
+    ) -> Result<Vec<FaultRecord>, String> {
+        let mut records = trace_run(
+            &self.file_data,
+            &self.config,
+            cycles,
+            RunType::RecordTrace,
+            false,
+            &[],
+        )?;
+        // Filter records according to fault type
+        fault_type.filter(&mut records, &self.cs);
+
+        Ok(records
+            .iter()
+            .filter_map(|record| match record {
+                TraceRecord::Instruction { index, .. } => Some(FaultRecord {
+                    index: *index,
+                    fault_type: fault_type.clone(),
+                }),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Randomly select fault candidates of the nominal program flow
+    ///
+    /// Up to `count` records of `enumerate_fault_candidates` are selected and returned
+    /// sorted by their index. With a seed in the configuration the selection is reproducible
+    pub fn sample_fault_candidates(
+        &self,
+        cycles: usize,
+        fault_type: &FaultType,
+        count: usize,
+    ) -> Result<Vec<FaultRecord>, String> {
+        let candidates = self.enumerate_fault_candidates(cycles, fault_type)?;
+        let mut rng = match self.config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let mut records: Vec<FaultRecord> = candidates
+            .choose_multiple(&mut rng, count)
+            .cloned()
+            .collect();
+        records.sort_by_key(|record| record.index);
+        Ok(records)
+    }
+
+    /// Create a fault record which is triggered at the n-th execution of the given address
+    ///
+    /// `pass` starts with 1 for the first execution. This models faults which only hit a
+    /// specific loop iteration. Returns None if the address is not executed `pass` times
+    pub fn fault_record_at_pass(
+        &self,
+        cycles: usize,
+        address: u64,
+        pass: usize,
+        fault_type: &FaultType,
+    ) -> Result<Option<FaultRecord>, String> {
+        if pass == 0 {
+            return Ok(None);
+        }
+        // Record the full program flow including repeated code
+        let records = trace_run(
+            &self.file_data,
+            &self.config,
+            cycles,
+            RunType::RecordTrace,
+            true,
+            &[],
+        )?;
+
+        Ok(records
+            .iter()
+            .filter_map(|record| match record {
+                TraceRecord::Instruction {
+                    address: record_address,
+                    index,
+                    ..
+                } if *record_address == address => Some(*index),
+                _ => None,
+            })
+            .nth(pass - 1)
+            .map(|index| FaultRecord {
+                index,
+                fault_type: fault_type.clone(),
+            }))
+    }
+
+    /// Get address and execution pass of the instruction at `index` of the nominal program flow
+    ///
+    /// `index` is the position in the trace of `record_ordered_trace`. The pass counts the
+    /// executions of the address up to the index (1 = first execution), so it can be
+    /// passed to `fault_record_at_pass` to fault the same iteration of a loop
+    pub fn execution_at_index(&self, cycles: usize, index: usize) -> Result<(u64, usize), String> {
+        let records = trace_run(
+            &self.file_data,
+            &self.config,
+            cycles,
+            RunType::RecordTrace,
+            true,
+            &[],
+        )?;
+
+        let address = records
+            .iter()
+            .find_map(|record| match record {
+                TraceRecord::Instruction {
+                    address,
+                    index: record_index,
+                    ..
+                } if *record_index == index => Some(*address),
+                _ => None,
+            })
+            .ok_or(format!("Index {} is not part of the program flow", index))?;
+        let pass = records
+            .iter()
+            .filter(|record| match record {
+                TraceRecord::Instruction {
+                    address: record_address,
+                    index: record_index,
+                    ..
+                } => *record_address == address && *record_index <= index,
+                _ => false,
+            })
+            .count();
+        Ok((address, pass))
+    }
+
+    /// Create a fault record which is triggered at the first instruction of the given symbol
+    ///
+    /// The Thumb bit of the symbol address is removed. An error is returned if the
+    /// symbol is unknown or not executed in the nominal program flow
+    pub fn fault_at_symbol(
+        &self,
+        cycles: usize,
+        name: &str,
+        fault_type: &FaultType,
+    ) -> Result<FaultRecord, String> {
+        self.fault_at_symbol_offset(cycles, name, 0, fault_type)
+    }
+
+    /// Create a fault record which is triggered at an instruction of the given symbol
+    ///
+    /// `instruction_index` counts the instructions from the start of the symbol
+    /// (0 = first instruction), so the fault stays at the same instruction if the
+    /// binary is recompiled. On ARM targets the instructions are counted in the
+    /// state of the program entry
+    pub fn fault_at_symbol_offset(
+        &self,
+        cycles: usize,
+        name: &str,
+        instruction_index: usize,
+        fault_type: &FaultType,
+    ) -> Result<FaultRecord, String> {
+        let address = self.symbol_address(name, instruction_index)?;
+        self.fault_record_at_pass(cycles, address, 1, fault_type)?
+            .ok_or(format!(
+                "Symbol {} at 0x{:X} is not executed",
+                name, address
+            ))
+    }
+
+    /// Get address of the instruction `instruction_index` of the given symbol
+    fn symbol_address(&self, name: &str, instruction_index: usize) -> Result<u64, String> {
+        let symbol = self
+            .file_data
+            .symbol_map
+            .get(name)
+            .ok_or(format!("Symbol {} not found", name))?;
+        let address = clear_thumb(symbol.st_value);
+        if instruction_index == 0 {
+            return Ok(address);
+        }
+
+        let mut simulation = Control::new(&self.file_data, &self.config)
+            .map_err(|e| format!("Simulation setup failed: {:?}", e))?;
+        simulation.start();
+        simulation
+            .instruction_address(address, instruction_index)
+            .map_err(|e| {
+                format!(
+                    "Failed to read instruction {} of {}: {:?}",
+                    instruction_index, name, e
+                )
+            })
+    }
+
+    /// Load attacks from a JSON file
+    ///
+    /// The file contains a list of attacks, each a list of faults:
+    /// `[[{"symbol": "main", "offset": 3, "fault_type": "glitch_1", "count": 1},
+    ///    {"address": "0x08000640", "fault_type": "regbf_r0_00000001", "count": 2,
+    ///     "trigger_count": 3}]]`
+    ///
+    /// The location is given by `address` (number, or string which is hex with a "0x"
+    /// prefix and decimal otherwise) or by `symbol` with an optional instruction `offset`.
+    /// `count` successive executions of the location are faulted, starting with execution
+    /// `trigger_count` (default: 1). `fault_type` is the name of the command line
+    /// (e.g. "glitch_3"). Unknown keys are rejected. The faults are resolved in the
+    /// nominal program flow and applied in program order
+    #[cfg(feature = "serde")]
+    pub fn load_attacks(
+        &self,
+        cycles: usize,
+        path: &Path,
+    ) -> Result<Vec<Vec<FaultRecord>>, String> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let attacks: Vec<Vec<AttackFileFault>> =
+            serde_json::from_reader(std::io::BufReader::new(file))
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+        attacks
+            .iter()
+            .map(|attack| {
+                let mut records = Vec::new();
+                for fault in attack {
+                    records.extend(self.load_fault(cycles, fault)?);
+                }
+                Ok(relative_indices(records))
+            })
+            .collect()
+    }
+
+    /// Create the fault records of one fault of an attack file
+    #[cfg(feature = "serde")]
+    fn load_fault(
+        &self,
+        cycles: usize,
+        fault: &AttackFileFault,
+    ) -> Result<Vec<FaultRecord>, String> {
+        let fault_type = get_fault_from(&fault.fault_type)?;
+        let address = match (&fault.address, &fault.symbol) {
+            (Some(AttackFileAddress::Number(address)), None) => *address,
+            (Some(AttackFileAddress::Text(address)), None) => match address.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => address.parse(),
+            }
+            .map_err(|_| format!("Invalid address {}", address))?,
+            (None, Some(name)) => self.symbol_address(name, fault.offset)?,
+            _ => {
+                return Err(format!(
+                    "Fault {} needs either an address or a symbol",
+                    fault.fault_type
+                ))
+            }
+        };
+        if fault.count == 0 || fault.trigger_count == Some(0) {
+            return Err(format!(
+                "Count and trigger count of fault {} must not be zero",
+                fault.fault_type
+            ));
+        }
+
+        let first = fault.trigger_count.unwrap_or(1);
+        (first..first + fault.count)
+            .map(|pass| {
+                self.fault_record_at_pass(cycles, address, pass, &fault_type)?
+                    .ok_or(format!(
+                        "Address 0x{:X} is not executed {} times",
+                        address, pass
+                    ))
+            })
+            .collect()
+    }
+
+    /// Run a list of fault attacks in parallel
+    ///
+    /// Every attack is a sequence of fault records (e.g. from
+    /// `FaultData::get_simulation_fault_records`). A simulation instance is created
+    /// for each job rayon splits the attack list into and reused for its attacks.
+    /// `threads` sets the number of worker threads (0 = all available cores).
+    /// Successful attacks are returned in the order of the given attack list,
+    /// independent of the number of threads. The attacks run with the fault
+    /// instruction limit of the configuration (default: derived from the nominal run)
+    pub fn run_faults_parallel(
+        &self,
+        cycles: usize,
+        attacks: &[Vec<FaultRecord>],
+        threads: usize,
+    ) -> Result<Vec<Vec<FaultData>>, String> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|e| format!("Failed to setup thread pool: {}", e))?;
+
+        let cycles = self.fault_cycles(cycles)?;
+        self.start_campaign();
+        let (file_data, config) = (&self.file_data, &self.config);
+        let (progress, success_count, cancel) = (&self.progress, &self.success_count, &self.cancel);
+        let results = pool.install(|| {
+            campaign_run(
+                file_data,
+                config,
+                progress,
+                success_count,
+                cancel,
+                cycles,
+                attacks,
+            )
+        })?;
+
+        Ok(results
+            .into_iter()
+            .flatten()
+            .filter_map(|(fault_data, _)| fault_data)
+            .collect())
+    }
+
+    /// Run the attacks of a campaign in parallel
+    ///
+    /// Attacks after a cancel are skipped and return None. Successful attacks are
+    /// counted and the progress callback is invoked after each attack
+    fn run_campaign(
+        &self,
+        cycles: usize,
+        attacks: &[Vec<FaultRecord>],
+    ) -> Result<Vec<Option<AttackResult>>, String> {
+        campaign_run(
+            &self.file_data,
+            &self.config,
+            &self.progress,
+            &self.success_count,
+            &self.cancel,
+            cycles,
+            attacks,
+        )
+    }
+
+    /// Run a list of fault attacks in parallel and tag them with their run state
+    ///
+    /// With `collect_all` every attack is returned with the state its run ended in,
+    /// e.g. `RunState::Failed` for attacks detected by the program and `RunState::Error`
+    /// for crashes. Otherwise only successful attacks are returned as with
+    /// `run_faults_parallel`. The result is in the order of the given attacks
+    pub fn run_faults_with_state(
+        &self,
+        cycles: usize,
+        attacks: &[Vec<FaultRecord>],
+        collect_all: bool,
+    ) -> Result<Vec<(Vec<FaultData>, RunState)>, String> {
+        let cycles = self.fault_cycles(cycles)?;
+        let (file_data, config) = (&self.file_data, &self.config);
+        let results: Result<Vec<(Vec<FaultData>, RunState)>, String> = attacks
+            .par_iter()
+            .map_init(
+                || new_simulation(file_data, config),
+                |simulation, records| {
+                    let simulation = simulation.as_mut().map_err(|e| e.clone())?;
+                    attack_run_state(simulation, cycles, records)
+                },
+            )
+            .collect();
+
+        Ok(results?
+            .into_iter()
+            .filter(|(_, state)| collect_all || *state == RunState::Success)
+            .collect())
+    }
+
+    /// Run a list of fault attacks and save the progress to a checkpoint file
+    ///
+    /// Attacks run in parallel in chunks of `interval` attacks. After each chunk the
+    /// number of processed attacks and the positions of the successful attacks are
+    /// written to `path`, so an interrupted campaign can be continued with `resume_faults`.
+    /// Successful attacks are returned in the order of the given attack list
+    #[cfg(feature = "serde")]
+    pub fn run_faults_checkpoint(
+        &self,
+        cycles: usize,
+        attacks: &[Vec<FaultRecord>],
+        path: &Path,
+        interval: usize,
+    ) -> Result<Vec<Vec<FaultData>>, String> {
+        self.run_from_checkpoint(
+            cycles,
+            attacks,
+            path,
+            interval,
+            Checkpoint::new(attacks.len()),
+        )
+    }
+
+    /// Continue an interrupted campaign of `run_faults_checkpoint`
+    ///
+    /// The attack list has to be the same as for the interrupted campaign. Attacks
+    /// processed before the checkpoint are skipped, only the successful ones are
+    /// simulated again to restore their fault data
+    #[cfg(feature = "serde")]
+    pub fn resume_faults(
+        &self,
+        cycles: usize,
+        path: &Path,
+        attacks: &[Vec<FaultRecord>],
+        interval: usize,
+    ) -> Result<Vec<Vec<FaultData>>, String> {
+        let checkpoint = Checkpoint::read(path)?;
+        if checkpoint.total != attacks.len() {
+            return Err(format!(
+                "Checkpoint of {} attacks does not match the {} given attacks",
+                checkpoint.total,
+                attacks.len()
+            ));
+        }
+        self.run_from_checkpoint(cycles, attacks, path, interval, checkpoint)
+    }
+
+    /// Run the attacks which are not processed in the checkpoint
+    #[cfg(feature = "serde")]
+    fn run_from_checkpoint(
+        &self,
+        cycles: usize,
+        attacks: &[Vec<FaultRecord>],
+        path: &Path,
+        interval: usize,
+        mut checkpoint: Checkpoint,
+    ) -> Result<Vec<Vec<FaultData>>, String> {
+        if interval == 0 {
+            return Err("Checkpoint interval must be greater than 0".to_string());
+        }
+
+        let cycles = self.fault_cycles(cycles)?;
+        self.start_campaign();
+        // Restore fault data of the successful attacks before the checkpoint
+        let successful: Vec<Vec<FaultRecord>> = checkpoint
+            .successful
+            .iter()
+            .map(|index| attacks[*index].clone())
+            .collect();
+        let mut results: Vec<Vec<FaultData>> = self
+            .run_attacks(cycles, &successful)?
+            .into_iter()
+            .flatten()
+            .collect();
+        self.success_count.store(results.len(), Ordering::Relaxed);
+
+        // A cancelled campaign keeps the last checkpoint, so it can be resumed
+        while checkpoint.next < attacks.len() && !self.cancel.load(Ordering::Relaxed) {
+            let start = checkpoint.next;
+            let end = (start + interval).min(attacks.len());
+            let chunk = self.run_attacks(cycles, &attacks[start..end])?;
+            for (index, result) in chunk.into_iter().enumerate() {
+                if let Some(fault_data) = result {
+                    self.success_count.fetch_add(1, Ordering::Relaxed);
+                    checkpoint.successful.push(start + index);
+                    results.push(fault_data);
+                }
+            }
+            // Save progress only after the whole chunk is processed
+            checkpoint.next = end;
+            checkpoint.write(path)?;
+            if let Some(progress) = &self.progress {
+                progress(end, attacks.len());
+            }
+        }
+        Ok(results)
+    }
+
+    /// Run attacks in parallel and return the fault data of each attack in the given order
+    ///
+    /// A simulation instance is created for each rayon job and reused for its attacks
+    #[cfg(feature = "serde")]
+    fn run_attacks(
+        &self,
+        cycles: usize,
+        attacks: &[Vec<FaultRecord>],
+    ) -> Result<Vec<Option<Vec<FaultData>>>, String> {
+        let (file_data, config) = (&self.file_data, &self.config);
+        attacks
+            .par_iter()
+            .map_init(
+                || new_simulation(file_data, config),
+                |simulation, records| {
+                    let simulation = simulation.as_mut().map_err(|e| e.clone())?;
+                    attack_run_with(simulation, cycles, records)
+                },
+            )
+            .collect()
+    }
+
+    /// Run all combinations of `depth` fault candidates (e.g. of `enumerate_fault_candidates`)
+    ///
+    /// The candidate indices are positions in the nominal program flow. They are converted
+    /// to the relative indices of a fault sequence, so faults are applied in program order.
+    /// The number of combinations grows with n! / (depth! * (n - depth)!), an optional
+    /// `cap` limits the number of executed combinations. Returns all successful attacks
+    pub fn run_combinations(
+        &self,
+        cycles: usize,
+        candidates: &[FaultRecord],
+        depth: usize,
+        cap: Option<usize>,
+    ) -> Result<Vec<Vec<FaultData>>, String> {
+        let mut candidates = candidates.to_vec();
+        candidates.sort_by_key(|record| record.index);
+
+        let attacks: Vec<Vec<FaultRecord>> = candidates
+            .iter()
+            .combinations(depth)
+            .take(cap.unwrap_or(usize::MAX))
+            .map(|combination| relative_indices(combination.into_iter().cloned().collect()))
+            .collect();
+        debug!("Number of combinations: {}", attacks.len());
+
+        let cycles = self.fault_cycles(cycles)?;
+        self.start_campaign();
+        Ok(self
+            .run_campaign(cycles, &attacks)?
+            .into_iter()
+            .flatten()
+            .filter_map(|(fault_data, _)| fault_data)
+            .collect())
+    }
+
+    /// Compare the successful attacks of two builds of a program
+    ///
+    /// The faults are located by symbol and instruction index, so the same attack is
+    /// applied to both builds although the addresses differ. Each fault is injected at
+    /// the first execution of its instruction, an attack with a fault at an instruction
+    /// which is not executed in a build is not successful there. Each build runs as a
+    /// campaign with its own counters, attacks skipped by a cancel are not successful
+    pub fn compare_campaigns(
+        a: &FaultAttacks,
+        b: &FaultAttacks,
+        cycles: usize,
+        attacks: &[Vec<SymbolFault>],
+    ) -> Result<CampaignComparison, String> {
+        let success_a = a.run_symbol_attacks(cycles, attacks)?;
+        let success_b = b.run_symbol_attacks(cycles, attacks)?;
+        let only = |first: &[bool], second: &[bool]| {
+            (0..attacks.len())
+                .filter(|index| first[*index] && !second[*index])
+                .collect()
+        };
+        Ok(CampaignComparison {
+            only_a: only(&success_a, &success_b),
+            only_b: only(&success_b, &success_a),
+        })
+    }
+
+    /// Check for each attack with symbol based faults if it is successful
+    fn run_symbol_attacks(
+        &self,
+        cycles: usize,
+        attacks: &[Vec<SymbolFault>],
+    ) -> Result<Vec<bool>, String> {
+        let first_index = self.first_executions(cycles)?;
+        // Attacks with faults which are not executed are None
+        let mut resolved = Vec::new();
+        for attack in attacks {
+            let mut records = Vec::new();
+            for fault in attack {
+                let address = self.symbol_address(&fault.symbol, fault.offset)?;
+                records.push(first_index.get(&address).map(|index| FaultRecord {
+                    index: *index,
+                    fault_type: fault.fault_type.clone(),
+                }));
+            }
+            resolved.push(records.into_iter().collect::<Option<Vec<_>>>());
+        }
+
+        let positions: Vec<usize> = (0..resolved.len())
+            .filter(|position| resolved[*position].is_some())
+            .collect();
+        let mut success = vec![false; resolved.len()];
+        let runnable: Vec<Vec<FaultRecord>> = resolved
+            .into_iter()
+            .flatten()
+            .map(relative_indices)
+            .collect();
+
+        let cycles = self.fault_cycles(cycles)?;
+        self.start_campaign();
+        for (position, result) in positions
+            .into_iter()
+            .zip(self.run_campaign(cycles, &runnable)?)
+        {
+            success[position] = matches!(result, Some((Some(_), _)));
+        }
+        Ok(success)
+    }
+
+    /// Create a single fault attack for each instruction in `start..end`
+    ///
+    /// The instruction sizes are read from the loaded code, so mixed 2 and 4 byte
+    /// Thumb instructions are handled. Each fault is injected at the first execution
+    /// of its instruction, instructions which are not executed are skipped
+    pub fn faults_in_range(
+        &self,
+        cycles: usize,
+        start: u64,
+        end: u64,
+        fault_type: &FaultType,
+    ) -> Result<Vec<Vec<FaultRecord>>, String> {
+        let mut simulation = Control::new(&self.file_data, &self.config)
+            .map_err(|e| format!("Simulation setup failed: {:?}", e))?;
+        simulation.start();
+        let first_index = self.first_executions(cycles)?;
+
+        let mut attacks = Vec::new();
+        let mut address = clear_thumb(start);
+        while address < end {
+            if let Some(index) = first_index.get(&address) {
+                attacks.push(vec![FaultRecord {
+                    index: *index,
+                    fault_type: fault_type.clone(),
+                }]);
+            }
+            address = simulation
+                .instruction_address(address, 1)
+                .map_err(|e| format!("Failed to read instruction at 0x{:X}: {:?}", address, e))?;
+        }
+        Ok(attacks)
+    }
+
+    /// Get index of the first execution of each address in the nominal program flow
+    fn first_executions(&self, cycles: usize) -> Result<HashMap<u64, usize>, String> {
+        let mut first_index = HashMap::new();
+        for record in trace_run(
+            &self.file_data,
+            &self.config,
+            cycles,
+            RunType::RecordTrace,
+            true,
+            &[],
+        )? {
+            if let TraceRecord::Instruction { address, index, .. } = record {
+                first_index.entry(address).or_insert(index);
+            }
+        }
+        Ok(first_index)
+    }
+
+    /// Reduce a successful attack to the decisive faults
+    ///
+    /// Each fault is removed one at a time. If the attack is still successful without
+    /// it, the fault is dropped. Indices of the following faults are adjusted to keep
+    /// the position in the program flow. Fails if the given attack is not successful
+    /// or a run returns an error
+    pub fn minimize_attack(
+        &self,
+        cycles: usize,
+        attack: &[FaultRecord],
+    ) -> Result<Vec<FaultRecord>, String> {
+        let cycles = self.fault_cycles(cycles)?;
+        let mut simulation = new_simulation(&self.file_data, &self.config)?;
+        if attack_run_with(&mut simulation, cycles, attack)?.is_none() {
+            return Err("Attack to minimize is not successful".to_string());
+        }
+        let mut records = attack.to_vec();
+        let mut position = 0;
+        while position < records.len() && records.len() > 1 {
+            // Remove fault and move its index to the following fault
+            let mut reduced = records.clone();
+            let removed = reduced.remove(position);
+            if let Some(next) = reduced.get_mut(position) {
+                next.index += removed.index;
+            }
+
+            match attack_run_with(&mut simulation, cycles, &reduced)? {
+                Some(_) => records = reduced,
+                None => position += 1,
+            }
+        }
+        Ok(records)
+    }
+
+    /// Run a list of fault attacks and stop at the first successful one
+    ///
+    /// Attacks are executed in the given order, remaining attacks are skipped
+    /// after the first success. All attacks run in the same simulation instance,
+    /// which is initialized again for each attack
+    pub fn run_faults_first(
+        &self,
+        cycles: usize,
+        attacks: &[Vec<FaultRecord>],
+    ) -> Result<Option<Vec<FaultData>>, String> {
+        let cycles = self.fault_cycles(cycles)?;
+        self.start_campaign();
+        let mut simulation = new_simulation(&self.file_data, &self.config)?;
+        for (position, records) in attacks.iter().enumerate() {
+            if self.cancel.load(Ordering::Relaxed) {
+                break;
+            }
+            let result = attack_run_with(&mut simulation, cycles, records)?;
+            if let Some(progress) = &self.progress {
+                progress(position + 1, attacks.len());
+            }
+            if let Some(fault_data) = result {
+                self.success_count.fetch_add(1, Ordering::Relaxed);
+                return Ok(Some(fault_data));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Classify the effect of each attack
+    ///
+    /// Attacks which are not successful are distinguished by comparing their program
+    /// flow with the nominal run. The result is in the order of the given attacks
+    pub fn classify_faults(
+        &self,
+        cycles: usize,
+        attacks: &[Vec<FaultRecord>],
+    ) -> Result<Vec<(Vec<FaultRecord>, FaultEffect)>, String> {
+        let nominal = executed_addresses(&trace_run(
+            &self.file_data,
+            &self.config,
+            cycles,
+            RunType::RecordFullTrace,
+            true,
+            &[],
+        )?);
+
+        let cycles = self.fault_cycles(cycles)?;
+        let (file_data, config) = (&self.file_data, &self.config);
+        attacks
+            .par_iter()
+            .map_init(
+                || new_simulation(file_data, config),
+                |simulation, records| {
+                    let simulation = simulation.as_mut().map_err(|e| e.clone())?;
+                    simulation.set_instruction_limit(cycles);
+                    let data =
+                        simulation.run_with_faults(RunType::RecordFullTrace, true, records)?;
+
+                    let effect = match (simulation.get_stop_reason(), data) {
+                        (Some(reason), _) if reason.is_error() => FaultEffect::Error,
+                        _ if simulation.get_state() == RunState::Success => FaultEffect::Exploit,
+                        (_, Data::Trace(trace)) if executed_addresses(&trace) == nominal => {
+                            FaultEffect::Ineffective
+                        }
+                        _ => FaultEffect::Changed,
+                    };
+                    Ok((records.clone(), effect))
+                },
+            )
+            .collect()
+    }
+
+    /// Run a campaign and measure the emulation time of each attack
+    ///
+    /// The attacks run like with `run_faults_parallel`, successful attacks also carry
+    /// their duration in `FaultData::run_duration`. The returned durations are in the
+    /// order of the given attacks and can be used to find attacks which end up in long
+    /// running loops. Attacks skipped by a cancel have no duration
+    pub fn profile_attacks(
+        &self,
+        cycles: usize,
+        attacks: &[Vec<FaultRecord>],
+    ) -> Result<Vec<Option<Duration>>, String> {
+        let cycles = self.fault_cycles(cycles)?;
+        self.start_campaign();
+        Ok(self
+            .run_campaign(cycles, attacks)?
+            .into_iter()
+            .map(|result| result.map(|(_, duration)| duration))
+            .collect())
     }
 
     /// Run single glitch attacks
@@ -109,6 +1208,7 @@ impl FaultAttacks {
     ) -> Result<(bool, usize), String> {
         let lists = get_fault_lists(groups); // Get all faults of all lists
         let mut any_success = false; // Track if any fault was successful
+        self.start_campaign();
 
         for list in lists {
             // Iterate over all faults in the list
@@ -117,8 +1217,12 @@ impl FaultAttacks {
                 let fault = get_fault_from(&fault).unwrap();
 
                 // Run simulation with fault
-                let fault_data =
-                    self.fault_simulation(cycles, &[fault.clone()], deep_analysis, prograss_bar)?;
+                let fault_data = self.run_fault_simulation(
+                    cycles,
+                    &[fault.clone()],
+                    deep_analysis,
+                    prograss_bar,
+                )?;
 
                 if !fault_data.is_empty() {
                     any_success = true;
@@ -130,6 +1234,9 @@ impl FaultAttacks {
                         return Ok((any_success, self.count_sum));
                     }
                 }
+                if self.cancel.load(Ordering::Relaxed) {
+                    return Ok((any_success, self.count_sum));
+                }
             }
         }
         Ok((any_success, self.count_sum))
@@ -149,6 +1256,7 @@ impl FaultAttacks {
     ) -> Result<(bool, usize), String> {
         let lists = get_fault_lists(groups); // Get all faults of all lists
         let mut any_success = false; // Track if any fault was successful
+        self.start_campaign();
 
         for list in lists {
             // Iterate over all faults in the list
@@ -158,8 +1266,12 @@ impl FaultAttacks {
                 let fault1 = get_fault_from(&t.0).unwrap();
                 let fault2 = get_fault_from(&t.1).unwrap();
 
-                let fault_data =
-                    self.fault_simulation(cycles, &[fault1, fault2], deep_analysis, prograss_bar)?;
+                let fault_data = self.run_fault_simulation(
+                    cycles,
+                    &[fault1, fault2],
+                    deep_analysis,
+                    prograss_bar,
+                )?;
 
                 if !fault_data.is_empty() {
                     any_success = true;
@@ -171,6 +1283,9 @@ impl FaultAttacks {
                         return Ok((any_success, self.count_sum));
                     }
                 }
+                if self.cancel.load(Ordering::Relaxed) {
+                    return Ok((any_success, self.count_sum));
+                }
             }
         }
         Ok((any_success, self.count_sum))
@@ -182,6 +1297,18 @@ impl FaultAttacks {
         faults: &[FaultType],
         deep_analysis: bool,
         prograss_bar: bool,
+    ) -> Result<Vec<Vec<FaultData>>, String> {
+        self.start_campaign();
+        self.run_fault_simulation(cycles, faults, deep_analysis, prograss_bar)
+    }
+
+    /// Run the fault simulation without resetting the campaign state
+    fn run_fault_simulation(
+        &mut self,
+        cycles: usize,
+        faults: &[FaultType],
+        deep_analysis: bool,
+        prograss_bar: bool,
     ) -> Result<Vec<Vec<FaultData>>, String> {
         //
         println!("Running simulation for faults: {faults:?}");
@@ -194,12 +1321,15 @@ impl FaultAttacks {
         // Run simulation to record normal fault program flow as a base for fault injection
         let mut records = trace_run(
             &self.file_data,
+            &self.config,
             cycles,
             RunType::RecordTrace,
             deep_analysis,
             &[],
         )?;
         debug!("Number of trace steps: {}", records.len());
+        let fault_cycles = self.fault_cycles(cycles)?;
+        debug!("Instruction limit of fault runs: {}", fault_cycles);
 
         let mut bar: Option<ProgressBar> = None;
         // Setup progress bar and channel for fault data
@@ -207,45 +1337,64 @@ impl FaultAttacks {
             bar = Some(ProgressBar::new(records.len() as u64));
         }
         let (sender, receiver) = channel();
+        let total = records.len();
+        let current = AtomicUsize::new(0);
 
         // Split faults into first and remaining faults
         let (first_fault, remaining_faults) = faults.split_first().unwrap();
         // Filter records according to fault type
         first_fault.filter(&mut records, &self.cs);
 
-        // Run main fault simulation loop
+        // Run main fault simulation loop. The simulation instance is created once per
+        // rayon job (not per thread) and reused for all records of the job
+        let (file_data, config) = (&self.file_data, &self.config);
         let n_result: Result<usize, String> = records
             .into_par_iter()
-            .map_with(sender, |s, record| -> Result<usize, String> {
-                if let Some(bar) = &bar {
-                    bar.inc(1);
-                }
+            .map_init(
+                move || (sender.clone(), new_simulation(file_data, config)),
+                |(s, simulation), record| -> Result<usize, String> {
+                    if let Some(bar) = &bar {
+                        bar.inc(1);
+                    }
+                    if self.cancel.load(Ordering::Relaxed) {
+                        return Ok(0);
+                    }
+                    let simulation = simulation.as_mut().map_err(|e| e.clone())?;
 
-                let number;
-                // Get index of the record
-                if let TraceRecord::Instruction { index, .. } = record {
-                    // Create a simulation fault record list with the first fault in the list
-                    let simulation_fault_records = vec![FaultRecord {
-                        index,
-                        fault_type: first_fault.clone(),
-                    }];
+                    let number;
+                    // Get index of the record
+                    if let TraceRecord::Instruction { index, .. } = record {
+                        // Create a simulation fault record list with the first fault in the list
+                        let simulation_fault_records = vec![FaultRecord {
+                            index,
+                            fault_type: first_fault.clone(),
+                        }];
 
-                    // Call recursive fault simulation with first simulation fault record
-                    number = Self::fault_simulation_inner(
-                        &self.file_data,
-                        cycles,
-                        remaining_faults,
-                        &simulation_fault_records,
-                        deep_analysis,
-                        s,
-                        &Disassembly::new(),
-                    )?;
-                } else {
-                    return Err("No instruction record found".to_string());
-                }
+                        // Call recursive fault simulation with first simulation fault record
+                        number = Self::fault_simulation_inner(
+                            simulation,
+                            cycles,
+                            fault_cycles,
+                            remaining_faults,
+                            &simulation_fault_records,
+                            deep_analysis,
+                            s,
+                            &Disassembly::with_target(
+                                self.config.target_arch,
+                                self.config.endianness,
+                            ),
+                        )?;
+                    } else {
+                        return Err("No instruction record found".to_string());
+                    }
 
-                Ok(number)
-            })
+                    self.success_count.fetch_add(number, Ordering::Relaxed);
+                    if let Some(progress) = &self.progress {
+                        progress(current.fetch_add(1, Ordering::Relaxed) + 1, total);
+                    }
+                    Ok(number)
+                },
+            )
             .sum();
 
         if let Some(bar) = bar {
@@ -256,8 +1405,15 @@ impl FaultAttacks {
         let n = n_result?;
         self.count_sum += n;
 
-        // Return collected successful attacks to caller
-        let data: Vec<_> = receiver.iter().collect();
+        // Return collected successful attacks to caller sorted by fault positions
+        // to get the same result independent of the thread scheduling
+        let mut data: Vec<Vec<FaultData>> = receiver.iter().collect();
+        data.sort_by_key(|fault_data| {
+            fault_data
+                .iter()
+                .map(|fault| fault.fault.index)
+                .collect::<Vec<usize>>()
+        });
         println!("-> {} attacks executed, {} successful", n, data.len());
         if data.is_empty() {
             Ok(Vec::new())
@@ -266,9 +1422,11 @@ impl FaultAttacks {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn fault_simulation_inner(
-        file_data: &ElfFile,
+        simulation: &mut Control,
         cycles: usize,
+        fault_cycles: usize,
         faults: &[FaultType],
         simulation_fault_records: &[FaultRecord],
         deep_analysis: bool,
@@ -280,12 +1438,12 @@ impl FaultAttacks {
         // Check if there are no remaining faults left
         if faults.is_empty() {
             // Run fault simulation. This is the end of the recursion
-            simulation_run(file_data, cycles, simulation_fault_records, s)?;
+            simulation_run(simulation, fault_cycles, simulation_fault_records, s)?;
             n += 1;
         } else {
             // Collect trace records with simulation fault records to get new running length (time)
-            let mut records = trace_run(
-                file_data,
+            let mut records = trace_run_with(
+                simulation,
                 cycles,
                 RunType::RecordTrace,
                 deep_analysis,
@@ -310,8 +1468,9 @@ impl FaultAttacks {
 
                     // Call recursive fault simulation with remaining faults
                     n += Self::fault_simulation_inner(
-                        file_data,
+                        simulation,
                         cycles,
+                        fault_cycles,
                         remaining_faults,
                         &index_simulation_fault_records,
                         deep_analysis,
@@ -326,38 +1485,248 @@ impl FaultAttacks {
     }
 }
 
+impl SimulationBuilder {
+    /// Check settings, load the elf file and build a ready fault attack instance
+    ///
+    /// Fails if no elf file is set. Target architecture and byte order are detected
+    /// from the elf file if not set
+    pub fn build(mut self) -> Result<FaultAttacks, String> {
+        let path = self
+            .elf_path
+            .take()
+            .ok_or("No elf file set for simulation".to_string())?;
+        let mut fault_attacks = FaultAttacks::with_config(path, SimulationConfig::default())?;
+
+        // Take detected values if not set
+        self.config.target_arch = self.target_arch.unwrap_or(fault_attacks.config.target_arch);
+        self.config.endianness = self.endianness.unwrap_or(fault_attacks.config.endianness);
+        self.config.validate()?;
+        fault_attacks.set_config(self.config);
+        Ok(fault_attacks)
+    }
+}
+
+/// Create a simulation instance for the runs of a campaign
+fn new_simulation<'a>(
+    file_data: &'a ElfFile,
+    config: &'a SimulationConfig,
+) -> Result<Control<'a>, String> {
+    Control::new(file_data, config).map_err(|e| format!("Simulation setup failed: {:?}", e))
+}
+
 /// Run the simulation with faults and return a trace of the program flow
 ///
-/// If the simulation fails, return an empty vector
-///
+/// Errors of the nominal run (no faults) are returned. If a faulted simulation
+/// fails, an empty vector is returned
 fn trace_run(
     file_data: &ElfFile,
+    config: &SimulationConfig,
     cycles: usize,
     run_type: RunType,
     deep_analysis: bool,
     records: &[FaultRecord],
 ) -> Result<Vec<TraceRecord>, String> {
-    let mut simulation = Control::new(file_data);
-    let data = simulation.run_with_faults(cycles, run_type, deep_analysis, records)?;
+    let mut simulation = new_simulation(file_data, config)?;
+    trace_run_with(&mut simulation, cycles, run_type, deep_analysis, records)
+}
+
+/// Record a trace like `trace_run` with an existing simulation instance
+fn trace_run_with(
+    simulation: &mut Control,
+    cycles: usize,
+    run_type: RunType,
+    deep_analysis: bool,
+    records: &[FaultRecord],
+) -> Result<Vec<TraceRecord>, String> {
+    simulation.set_instruction_limit(cycles);
+    // All fault results depend on the nominal run, so its errors are returned
+    if records.is_empty() {
+        return simulation.try_record_trace(run_type, deep_analysis, records);
+    }
+    let data = simulation.run_with_faults(run_type, deep_analysis, records)?;
     match data {
         Data::Trace(trace) => Ok(trace),
         _ => Ok(Vec::new()),
     }
 }
 
+/// Convert indices in the nominal program flow to the relative indices of a fault sequence
+///
+/// The records are sorted, so faults are applied in program order
+fn relative_indices(mut records: Vec<FaultRecord>) -> Vec<FaultRecord> {
+    records.sort_by_key(|record| record.index);
+    let mut last_index = 0;
+    for record in records.iter_mut() {
+        let index = record.index;
+        record.index -= last_index;
+        last_index = index;
+    }
+    records
+}
+
+/// Get executed addresses of a trace in execution order
+fn executed_addresses(trace: &[TraceRecord]) -> Vec<u64> {
+    trace
+        .iter()
+        .filter_map(|record| match record {
+            TraceRecord::Instruction { address, .. } => Some(*address),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Write trace as CSV (address, size, count) sorted by address
+fn write_trace_csv<W: Write>(mut writer: W, trace: &[(u64, usize)]) -> std::io::Result<()> {
+    let mut entries: BTreeMap<u64, (usize, usize)> = BTreeMap::new();
+    for (address, size) in trace {
+        entries.entry(*address).or_insert((*size, 0)).1 += 1;
+    }
+
+    writeln!(writer, "address,size,count")?;
+    for (address, (size, count)) in entries {
+        writeln!(writer, "0x{:08x},{},{}", address, size, count)?;
+    }
+    writer.flush()
+}
+
 fn simulation_run(
-    file_data: &ElfFile,
+    simulation: &mut Control,
     cycles: usize,
     records: &[FaultRecord],
     s: &mut Sender<Vec<FaultData>>,
 ) -> Result<(), String> {
-    let mut simulation = Control::new(file_data);
-    let data = simulation.run_with_faults(cycles, RunType::Run, false, records)?;
-    if let Data::Fault(fault) = data {
-        if !fault.is_empty() {
-            s.send(fault).unwrap();
-        }
+    if let Some(fault) = attack_run_with(simulation, cycles, records)? {
+        s.send(fault).unwrap();
     }
 
     Ok(())
 }
+
+/// Run the simulation with faults and return the fault data if the attack was successful
+///
+/// The instance is initialized by the run, so it can be reused for the next attack
+fn attack_run_with(
+    simulation: &mut Control,
+    cycles: usize,
+    records: &[FaultRecord],
+) -> Result<Option<Vec<FaultData>>, String> {
+    simulation.set_instruction_limit(cycles);
+    let data = simulation.run_with_faults(RunType::Run, false, records)?;
+    match data {
+        Data::Fault(fault) if !fault.is_empty() => Ok(Some(fault)),
+        _ => Ok(None),
+    }
+}
+
+/// Run the attacks of a campaign in parallel, see `FaultAttacks::run_campaign`
+///
+/// The fields of the campaign are passed separately, as `FaultAttacks` can not be
+/// shared between threads
+fn campaign_run(
+    file_data: &ElfFile,
+    config: &SimulationConfig,
+    progress: &Option<ProgressFn>,
+    success_count: &AtomicUsize,
+    cancel: &AtomicBool,
+    cycles: usize,
+    attacks: &[Vec<FaultRecord>],
+) -> Result<Vec<Option<AttackResult>>, String> {
+    let current = AtomicUsize::new(0);
+    attacks
+        .par_iter()
+        .map_init(
+            || new_simulation(file_data, config),
+            |simulation, records| {
+                if cancel.load(Ordering::Relaxed) {
+                    return Ok(None);
+                }
+                let simulation = simulation.as_mut().map_err(|e| e.clone())?;
+                let result = attack_run_with(simulation, cycles, records)?;
+                if result.is_some() {
+                    success_count.fetch_add(1, Ordering::Relaxed);
+                }
+                if let Some(progress) = progress {
+                    progress(current.fetch_add(1, Ordering::Relaxed) + 1, attacks.len());
+                }
+                Ok(Some((result, simulation.get_run_duration())))
+            },
+        )
+        .collect()
+}
+
+/// Run one attack and return its fault data with the resulting run state
+///
+/// Runs stopped by an emulation error are reported as `RunState::Error`
+fn attack_run_state(
+    simulation: &mut Control,
+    cycles: usize,
+    records: &[FaultRecord],
+) -> Result<(Vec<FaultData>, RunState), String> {
+    simulation.set_instruction_limit(cycles);
+    let data = simulation.run_with_faults(RunType::Run, false, records)?;
+    match (data, simulation.get_stop_reason()) {
+        (Data::Fault(fault), _) => Ok((fault, RunState::Success)),
+        (_, Some(reason)) if reason.is_error() => {
+            Ok((simulation.get_fault_data(), RunState::Error))
+        }
+        _ => Ok((simulation.get_fault_data(), simulation.get_state())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fault_cycles_from_nominal_run() {
+        let mut attack =
+            FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+        let derived = attack.fault_cycles(2000).unwrap();
+        assert!(derived > 0 && derived <= 2000, "{}", derived);
+        // Limited by the trace limit
+        assert_eq!(attack.fault_cycles(10).unwrap(), 10);
+        // Configured limit
+        attack.config.fault_instruction_limit = Some(100);
+        assert_eq!(attack.fault_cycles(2000).unwrap(), 100);
+    }
+
+    #[test]
+    fn execution_of_trace_index() {
+        let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+        let trace = attack.record_ordered_trace(2000).unwrap();
+        // Last instruction of a loop
+        let index = (0..trace.len())
+            .rev()
+            .find(|index| {
+                trace[..*index]
+                    .iter()
+                    .any(|(address, _)| *address == trace[*index].0)
+            })
+            .unwrap();
+
+        let (address, pass) = attack.execution_at_index(2000, index).unwrap();
+        assert_eq!(address, trace[index].0);
+        assert!(pass > 1);
+        let glitch: FaultType = Glitch::new(1);
+        let record = attack
+            .fault_record_at_pass(2000, address, pass, &glitch)
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.index, index);
+        assert!(attack.execution_at_index(2000, trace.len()).is_err());
+    }
+
+    #[test]
+    fn trace_csv_sorted_with_count() {
+        let mut output = Vec::new();
+        write_trace_csv(
+            &mut output,
+            &[(0x8000010, 2), (0x8000000, 4), (0x8000010, 2)],
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "address,size,count\n0x08000000,4,1\n0x08000010,2,2\n"
+        );
+    }
+}
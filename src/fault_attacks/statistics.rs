@@ -0,0 +1,123 @@
+use super::{FaultEffect, FaultRecord};
+use itertools::Itertools;
+use std::{collections::BTreeMap, fmt};
+
+/// Effect counts of the attacks of one fault type
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FaultTypeStats {
+    pub exploit: usize,
+    pub ineffective: usize,
+    pub changed: usize,
+    pub error: usize,
+}
+
+impl FaultTypeStats {
+    /// Number of all attacks
+    pub fn total(&self) -> usize {
+        self.exploit + self.ineffective + self.changed + self.error
+    }
+
+    /// Share of successful attacks (0.0 - 1.0)
+    pub fn success_rate(&self) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => self.exploit as f64 / total as f64,
+        }
+    }
+}
+
+/// Statistics of a fault campaign per fault type
+///
+/// Attacks are keyed by the names of their fault types, e.g. "Glitch" or
+/// "Glitch + Command BitFlip" for attacks with different fault types
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CampaignStats {
+    pub fault_types: BTreeMap<String, FaultTypeStats>,
+}
+
+impl CampaignStats {
+    /// Aggregate the classified attacks of `FaultAttacks::classify_faults`
+    pub fn from_classified(results: &[(Vec<FaultRecord>, FaultEffect)]) -> Self {
+        let mut stats = Self::default();
+        for (records, effect) in results {
+            let entry = stats.fault_types.entry(attack_name(records)).or_default();
+            match effect {
+                FaultEffect::Exploit => entry.exploit += 1,
+                FaultEffect::Ineffective => entry.ineffective += 1,
+                FaultEffect::Changed => entry.changed += 1,
+                FaultEffect::Error => entry.error += 1,
+            }
+        }
+        stats
+    }
+}
+
+impl fmt::Display for CampaignStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, stats) in &self.fault_types {
+            writeln!(
+                f,
+                "{}: {} attacks, {} successful ({:.1}%), {} crashed, {} changed, {} without effect",
+                name,
+                stats.total(),
+                stats.exploit,
+                stats.success_rate() * 100.0,
+                stats.error,
+                stats.changed,
+                stats.ineffective
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of `FaultAttacks::compare_campaigns`
+///
+/// Attacks are given by their position in the compared attack list
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CampaignComparison {
+    /// Attacks which are only successful on the first build
+    pub only_a: Vec<usize>,
+    /// Attacks which are only successful on the second build
+    pub only_b: Vec<usize>,
+}
+
+/// Get name of an attack out of its fault type names
+fn attack_name(records: &[FaultRecord]) -> String {
+    records
+        .iter()
+        .map(|record| record.fault_type.name())
+        .unique()
+        .join(" + ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fault_attacks::faults::{CmdBitFlip, Glitch};
+
+    #[test]
+    fn stats_per_fault_type() {
+        let glitch = vec![FaultRecord {
+            index: 1,
+            fault_type: Glitch::new(1),
+        }];
+        let bitflip = vec![FaultRecord {
+            index: 1,
+            fault_type: CmdBitFlip::new(1),
+        }];
+        let stats = CampaignStats::from_classified(&[
+            (glitch.clone(), FaultEffect::Exploit),
+            (glitch.clone(), FaultEffect::Error),
+            (glitch, FaultEffect::Ineffective),
+            (bitflip, FaultEffect::Changed),
+        ]);
+
+        let glitch_stats = stats.fault_types["Glitch"];
+        assert_eq!(glitch_stats.total(), 3);
+        assert_eq!(glitch_stats.exploit, 1);
+        assert!((glitch_stats.success_rate() - 1.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(stats.fault_types["Command BitFlip"].changed, 1);
+        assert_eq!(stats.fault_types["Command BitFlip"].success_rate(), 0.0);
+    }
+}
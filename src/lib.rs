@@ -1,9 +1,40 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
+
+pub mod data;
+#[cfg(feature = "std")]
 mod disassembly;
+#[cfg(feature = "std")]
 mod elf_file;
+#[cfg(feature = "std")]
 mod fault_attacks;
+#[cfg(feature = "std")]
 mod simulation;
 
+#[cfg(feature = "std")]
 pub mod prelude {
-    pub use crate::fault_attacks::{faults::*, FaultAttacks};
-    pub use crate::simulation::record::TraceRecord;
+    pub use crate::disassembly::Disassembly;
+    pub use crate::elf_file::ElfFile;
+    pub use crate::fault_attacks::{
+        faults::*,
+        statistics::{CampaignComparison, CampaignStats, FaultTypeStats},
+        FaultAttacks, FaultEffect, SymbolFault,
+    };
+    pub use crate::simulation::config::{
+        DecisionHandler, MmioHandler, SimulationBuilder, SimulationConfig, StateCallback,
+        SystemRegisters, TriggerRegion,
+    };
+    #[cfg(feature = "advanced")]
+    pub use crate::simulation::cpu::CpuState;
+    pub use crate::simulation::cpu::{
+        Cpu, Endianness, InvalidAccess, MemoryLayout, RunState, Snapshot, StopReason, TargetArch,
+    };
+    pub use crate::simulation::fault_data::FaultData;
+    pub use crate::simulation::record::{FaultRecord, TraceRecord, WatchRecord, WriteRecord};
+    #[cfg(feature = "serde")]
+    pub use crate::simulation::serialize::Checkpoint;
+    pub use crate::simulation::{
+        Control, Data, ProgramCheckError, RunType, DEFAULT_INSTRUCTION_LIMIT,
+    };
+    pub use unicorn_engine::unicorn_const::{MemType, Permission};
 }
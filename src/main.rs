@@ -46,7 +46,7 @@ struct Args {
     deep_analysis: bool,
 
     /// Maximum number of instructions to be executed
-    #[arg(short, long, default_value_t = 2000)]
+    #[arg(short, long, default_value_t = DEFAULT_INSTRUCTION_LIMIT)]
     max_instructions: usize,
 
     /// Load elf file w/o compilation step
@@ -64,6 +64,11 @@ struct Args {
     /// Don't stop on first successful fault injection
     #[arg(short, long, default_value_t = false)]
     run_through: bool,
+
+    /// Export successful attacks to a JSON file
+    #[cfg(feature = "serde")]
+    #[arg(long)]
+    json: Option<PathBuf>,
 }
 
 /// Program to simulate fault injections on ARMv8-M processors (e.g. M33)
@@ -177,6 +182,12 @@ fn main() -> Result<(), String> {
     // Pretty print fault data
     attack_sim.print_fault_data();
 
+    // Export fault data
+    #[cfg(feature = "serde")]
+    if let Some(path) = &args.json {
+        attack_sim.export_faults_json(path)?;
+    }
+
     println!("Overall tests executed {}", attack_sim.count_sum);
 
     if args.analysis {
@@ -0,0 +1,629 @@
+use super::cpu::{Endianness, MemoryLayout, RunState, TargetArch, MINIMUM_MEMORY_SIZE};
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::Arc;
+use unicorn_engine::unicorn_const::Permission;
+
+/// Read handler of a MMIO peripheral: (offset, size) -> value
+pub type MmioReadFn = Arc<dyn Fn(u64, usize) -> u64 + Send + Sync>;
+/// Write handler of a MMIO peripheral: (offset, size, value)
+pub type MmioWriteFn = Arc<dyn Fn(u64, usize, u64) + Send + Sync>;
+
+/// Handler of the decision breakpoint: (success run) -> memory writes (address, data)
+pub type DecisionFn = Arc<dyn Fn(bool) -> Vec<(u64, Vec<u8>)> + Send + Sync>;
+
+/// Callback of run state changes: (new state, program counter)
+pub type StateFn = Arc<dyn Fn(RunState, u64) + Send + Sync>;
+
+/// User defined callback of run state changes
+///
+/// The callback is called from the hook which changes the state. It has no
+/// access to the emulator, so it can't re-enter the running simulation
+#[derive(Clone)]
+pub struct StateCallback {
+    pub callback_fn: StateFn,
+}
+
+impl Debug for StateCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "StateCallback")
+    }
+}
+
+/// User defined handler of the decision breakpoint
+///
+/// The handler is called when the symbol is executed and replaces the default
+/// handling of `decision_activation`, e.g. to emulate the load of a boot image
+#[derive(Clone)]
+pub struct DecisionHandler {
+    pub symbol: String,
+    pub handler_fn: DecisionFn,
+}
+
+impl Debug for DecisionHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DecisionHandler ({})", self.symbol)
+    }
+}
+
+/// Named trigger address of a security check (e.g. secure boot, debug unlock)
+///
+/// A write of one of the sentinel values to the address ends the run with the
+/// mapped state like a write to the auth address. Other values are an error
+#[derive(Clone, Debug)]
+pub struct TriggerRegion {
+    pub name: String,
+    pub address: u64,
+    pub outcomes: Vec<(u64, RunState)>,
+}
+
+/// Initial values of the Cortex-M system registers
+///
+/// The default matches the state after reset: privileged thread mode on the main
+/// stack with interrupts enabled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SystemRegisters {
+    /// CONTROL register
+    pub control: u32,
+    /// PRIMASK register
+    pub primask: u32,
+    /// Vector table offset (VTOR) in the system control block.
+    /// None = start of the first executable segment
+    pub vtor: Option<u32>,
+}
+
+/// User defined MMIO peripheral
+///
+/// The offset given to the handlers is relative to the base address
+#[derive(Clone)]
+pub struct MmioHandler {
+    pub base: u64,
+    pub size: usize,
+    pub read_fn: MmioReadFn,
+    pub write_fn: MmioWriteFn,
+}
+
+impl Debug for MmioHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "MmioHandler (0x{:08x}, size: 0x{:x})",
+            self.base, self.size
+        )
+    }
+}
+
+/// Configuration of the simulated target
+///
+/// The configuration is shared by all simulation instances of a fault attack campaign
+#[derive(Clone, Debug)]
+pub struct SimulationConfig {
+    /// Memory layout of the target
+    pub memory_layout: MemoryLayout,
+    /// Architecture of the target
+    pub target_arch: TargetArch,
+    /// Byte order of the target
+    pub endianness: Endianness,
+    /// Value written to the auth address to signal a successful run
+    pub success_marker: u64,
+    /// Value written to the auth address to signal a failed run
+    pub failure_marker: u64,
+    /// User defined MMIO peripherals
+    pub mmio_handlers: Vec<MmioHandler>,
+    /// Wall-clock timeout of one emulation run in seconds (0 = no timeout).
+    /// This is independent of the instruction limit
+    pub timeout_secs: u64,
+    /// Maximum number of executed instructions of a run (None = only the limit of the
+    /// campaign). Simulation instances start with this limit and never exceed it
+    pub instruction_limit: Option<usize>,
+    /// Maximum number of executed instructions of a run with faults (None = 1.5 times
+    /// the length of the nominal run). Faulted runs often crash or loop, so a limit
+    /// below the trace limit ends them earlier
+    pub fault_instruction_limit: Option<usize>,
+    /// Suppress the serial output (printf) of the program
+    pub deactivate_print: bool,
+    /// Seed for randomized fault selection (None = random seed)
+    pub seed: Option<u64>,
+    /// Addresses which must not be executed, reaching one counts as successful attack
+    pub guard_addresses: Vec<u64>,
+    /// Custom handler of the decision breakpoint (None = `decision_activation`)
+    pub decision_handler: Option<DecisionHandler>,
+    /// Callback of run state changes (e.g. for live monitoring)
+    pub state_callback: Option<StateCallback>,
+    /// Keep running after the auth address is written, e.g. to check later integrity checks
+    pub continue_after_auth: bool,
+    /// Record all memory writes of a run up to the given number of entries
+    /// (None = disabled). This slows down every run, so it is meant for the
+    /// replay of single attacks rather than whole campaigns
+    pub write_log_limit: Option<usize>,
+    /// Permission of the executable program segments (None = ELF segment flags).
+    /// Pages shared with data segments keep the permissions of both
+    pub code_permission: Option<Permission>,
+    /// Map unmapped memory on an invalid access and keep running. The access is
+    /// still recorded and the run ends in the error state unless a later decision
+    /// changes it
+    pub continue_after_invalid_access: bool,
+    /// Record the lowest stack pointer of each run. This reads the stack pointer
+    /// on every executed instruction
+    pub track_stack_usage: bool,
+    /// Count the executed instructions of each run. This calls a hook on every
+    /// executed instruction
+    pub count_instructions: bool,
+    /// Count the clock cycles of each run with a simple timing model of the
+    /// instructions. This reads the code of every executed instruction
+    pub count_cycles: bool,
+    /// Stop a run in the error state after the given number of instructions
+    /// without executing a new address (None = no loop detection)
+    pub loop_limit: Option<usize>,
+    /// Pattern written to the uninitialized RAM and the stack before each run
+    /// (None = zero). With a pattern the stack is also written if it is part of a segment
+    pub memory_fill_pattern: Option<u32>,
+    /// Additional named trigger addresses besides the auth address
+    pub trigger_regions: Vec<TriggerRegion>,
+    /// Initialize the system registers of Cortex-M targets before each run
+    /// (None = registers are left to the emulator)
+    pub system_registers: Option<SystemRegisters>,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            memory_layout: MemoryLayout::default(),
+            target_arch: TargetArch::default(),
+            endianness: Endianness::default(),
+            success_marker: 0x11111111,
+            failure_marker: 0x22222222,
+            mmio_handlers: Vec::new(),
+            timeout_secs: 1,
+            instruction_limit: None,
+            fault_instruction_limit: None,
+            deactivate_print: true,
+            seed: None,
+            guard_addresses: Vec::new(),
+            decision_handler: None,
+            state_callback: None,
+            continue_after_auth: false,
+            write_log_limit: None,
+            code_permission: None,
+            continue_after_invalid_access: false,
+            track_stack_usage: false,
+            count_instructions: false,
+            count_cycles: false,
+            loop_limit: None,
+            memory_fill_pattern: None,
+            trigger_regions: Vec::new(),
+            system_registers: None,
+        }
+    }
+}
+
+impl SimulationConfig {
+    /// Add a handler for a custom MMIO peripheral (e.g. RNG or status register)
+    ///
+    /// The region is mapped in every simulation instance and released with it.
+    /// Handlers are shared between parallel simulations, so any state has to be
+    /// synchronized by the handler itself
+    pub fn add_mmio_handler<R, W>(&mut self, base: u64, size: usize, read_fn: R, write_fn: W)
+    where
+        R: Fn(u64, usize) -> u64 + Send + Sync + 'static,
+        W: Fn(u64, usize, u64) + Send + Sync + 'static,
+    {
+        self.mmio_handlers.push(MmioHandler {
+            base,
+            size,
+            read_fn: Arc::new(read_fn),
+            write_fn: Arc::new(write_fn),
+        });
+    }
+
+    /// Set a custom handler which is called when the given symbol is executed
+    ///
+    /// The handler gets the run type (true: success run) and returns the memory
+    /// writes to be done, e.g. the boot image or decision data
+    pub fn set_decision_handler<F>(&mut self, symbol: &str, handler_fn: F)
+    where
+        F: Fn(bool) -> Vec<(u64, Vec<u8>)> + Send + Sync + 'static,
+    {
+        self.decision_handler = Some(DecisionHandler {
+            symbol: symbol.to_string(),
+            handler_fn: Arc::new(handler_fn),
+        });
+    }
+
+    /// Set a callback which is called on each change of the run state
+    ///
+    /// The callback gets the new state and the program counter of the instruction
+    /// which caused the change. As the configuration is shared, the callback is
+    /// called by all parallel simulations
+    pub fn set_state_callback<F>(&mut self, callback_fn: F)
+    where
+        F: Fn(RunState, u64) + Send + Sync + 'static,
+    {
+        self.state_callback = Some(StateCallback {
+            callback_fn: Arc::new(callback_fn),
+        });
+    }
+
+    /// Add a named trigger address with its sentinel values and run states
+    ///
+    /// The page of the address is mapped if it is not part of the program memory
+    pub fn add_trigger_region(&mut self, name: &str, address: u64, outcomes: &[(u64, RunState)]) {
+        self.trigger_regions.push(TriggerRegion {
+            name: name.to_string(),
+            address,
+            outcomes: outcomes.to_vec(),
+        });
+    }
+
+    /// Check configuration for invalid settings
+    pub fn validate(&self) -> Result<(), String> {
+        if self.instruction_limit == Some(0) {
+            return Err("Instruction limit must not be zero".to_string());
+        }
+        if self.fault_instruction_limit == Some(0) {
+            return Err("Fault instruction limit must not be zero".to_string());
+        }
+        if let (Some(limit), Some(fault_limit)) =
+            (self.instruction_limit, self.fault_instruction_limit)
+        {
+            if fault_limit > limit {
+                return Err(format!(
+                    "Fault instruction limit {} exceeds the instruction limit {}",
+                    fault_limit, limit
+                ));
+            }
+        }
+        if self.loop_limit == Some(0) {
+            return Err("Loop limit must not be zero".to_string());
+        }
+        if self.memory_layout.stack_size == Some(0) {
+            return Err("Stack size must not be zero".to_string());
+        }
+        let layout = &self.memory_layout;
+        if layout.io_size == 0
+            || layout.io_size & (MINIMUM_MEMORY_SIZE - 1) != 0
+            || layout.io_base & (MINIMUM_MEMORY_SIZE as u64 - 1) != 0
+        {
+            return Err(format!(
+                "Serial io region at 0x{:08x} with size 0x{:x} is not page aligned",
+                layout.io_base, layout.io_size
+            ));
+        }
+        if self.success_marker == self.failure_marker {
+            return Err("Success and failure marker must be different".to_string());
+        }
+
+        // Check for overlapping io regions
+        let mut regions = vec![
+            ("auth", self.memory_layout.auth_base, MINIMUM_MEMORY_SIZE),
+            ("io", self.memory_layout.io_base, self.memory_layout.io_size),
+        ];
+        for handler in &self.mmio_handlers {
+            if handler.size == 0 {
+                return Err(format!("{:?} has no size", handler));
+            }
+            regions.push(("mmio", handler.base, handler.size));
+        }
+        for (index, trigger) in self.trigger_regions.iter().enumerate() {
+            if trigger.outcomes.is_empty() {
+                return Err(format!("Trigger {} has no sentinel values", trigger.name));
+            }
+            if self.trigger_regions[..index]
+                .iter()
+                .any(|other| other.name == trigger.name || other.address == trigger.address)
+            {
+                return Err(format!(
+                    "Trigger {} at 0x{:08x} is defined twice",
+                    trigger.name, trigger.address
+                ));
+            }
+            // Triggers may share pages with each other, but not with io regions
+            if let Some((name, base, _)) = regions.iter().find(|(_, base, size)| {
+                trigger.address + 4 > *base && trigger.address < base + *size as u64
+            }) {
+                return Err(format!(
+                    "Trigger {} at 0x{:08x} overlaps {} at 0x{:08x}",
+                    trigger.name, trigger.address, name, base
+                ));
+            }
+        }
+        for (index, (name, base, size)) in regions.iter().enumerate() {
+            for (other_name, other_base, other_size) in &regions[index + 1..] {
+                if *base < other_base + *other_size as u64 && *other_base < base + *size as u64 {
+                    return Err(format!(
+                        "Memory region {} at 0x{:08x} overlaps {} at 0x{:08x}",
+                        name, base, other_name, other_base
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builder for a simulation configuration
+///
+/// Collects all settings of the simulation in one place and checks them for
+/// invalid combinations before a simulation is started
+#[derive(Debug, Default)]
+pub struct SimulationBuilder {
+    pub(crate) elf_path: Option<PathBuf>,
+    pub(crate) config: SimulationConfig,
+    pub(crate) target_arch: Option<TargetArch>,
+    pub(crate) endianness: Option<Endianness>,
+}
+
+impl SimulationBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set elf file of the program under investigation
+    pub fn elf(mut self, path: PathBuf) -> Self {
+        self.elf_path = Some(path);
+        self
+    }
+
+    /// Set memory layout of the target
+    pub fn memory_layout(mut self, memory_layout: MemoryLayout) -> Self {
+        self.config.memory_layout = memory_layout;
+        self
+    }
+
+    /// Set target architecture (default: detected from the elf file)
+    pub fn target_arch(mut self, target_arch: TargetArch) -> Self {
+        self.target_arch = Some(target_arch);
+        self
+    }
+
+    /// Set byte order of the target (default: detected from the elf file)
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = Some(endianness);
+        self
+    }
+
+    /// Set maximum number of executed instructions of all runs
+    pub fn instruction_limit(mut self, instruction_limit: usize) -> Self {
+        self.config.instruction_limit = Some(instruction_limit);
+        self
+    }
+
+    /// Set maximum number of executed instructions of runs with faults
+    pub fn fault_instruction_limit(mut self, fault_instruction_limit: usize) -> Self {
+        self.config.fault_instruction_limit = Some(fault_instruction_limit);
+        self
+    }
+
+    /// Set wall-clock timeout of one emulation run in seconds
+    pub fn timeout_secs(mut self, timeout_secs: u64) -> Self {
+        self.config.timeout_secs = timeout_secs;
+        self
+    }
+
+    /// Suppress the serial output (printf) of the program
+    pub fn deactivate_print(mut self, deactivate_print: bool) -> Self {
+        self.config.deactivate_print = deactivate_print;
+        self
+    }
+
+    /// Set seed for randomized fault selection to get reproducible campaigns
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    /// Set a custom handler of the decision breakpoint
+    pub fn decision_handler<F>(mut self, symbol: &str, handler_fn: F) -> Self
+    where
+        F: Fn(bool) -> Vec<(u64, Vec<u8>)> + Send + Sync + 'static,
+    {
+        self.config.set_decision_handler(symbol, handler_fn);
+        self
+    }
+
+    /// Set a callback of run state changes
+    pub fn state_callback<F>(mut self, callback_fn: F) -> Self
+    where
+        F: Fn(RunState, u64) + Send + Sync + 'static,
+    {
+        self.config.set_state_callback(callback_fn);
+        self
+    }
+
+    /// Record the lowest stack pointer of each run
+    pub fn track_stack_usage(mut self, track_stack_usage: bool) -> Self {
+        self.config.track_stack_usage = track_stack_usage;
+        self
+    }
+
+    /// Count the executed instructions of each run
+    pub fn count_instructions(mut self, count_instructions: bool) -> Self {
+        self.config.count_instructions = count_instructions;
+        self
+    }
+
+    /// Count the clock cycles of each run
+    pub fn count_cycles(mut self, count_cycles: bool) -> Self {
+        self.config.count_cycles = count_cycles;
+        self
+    }
+
+    /// Detect endless loops after the given number of instructions without a new address
+    pub fn loop_limit(mut self, loop_limit: usize) -> Self {
+        self.config.loop_limit = Some(loop_limit);
+        self
+    }
+
+    /// Keep running after the auth address is written
+    pub fn continue_after_auth(mut self, continue_after_auth: bool) -> Self {
+        self.config.continue_after_auth = continue_after_auth;
+        self
+    }
+
+    /// Record memory writes of each run up to `limit` entries
+    pub fn write_log_limit(mut self, limit: usize) -> Self {
+        self.config.write_log_limit = Some(limit);
+        self
+    }
+
+    /// Map the executable program segments with the given permission,
+    /// e.g. `Permission::READ | Permission::EXEC` for MPU protected flash
+    pub fn code_permission(mut self, permission: Permission) -> Self {
+        self.config.code_permission = Some(permission);
+        self
+    }
+
+    /// Keep running after an access to unmapped memory
+    pub fn continue_after_invalid_access(mut self, continue_after_invalid_access: bool) -> Self {
+        self.config.continue_after_invalid_access = continue_after_invalid_access;
+        self
+    }
+
+    /// Fill uninitialized RAM and the stack with a pattern before each run
+    pub fn memory_fill_pattern(mut self, pattern: u32) -> Self {
+        self.config.memory_fill_pattern = Some(pattern);
+        self
+    }
+
+    /// Add a named trigger address with its sentinel values and run states
+    pub fn trigger_region(
+        mut self,
+        name: &str,
+        address: u64,
+        outcomes: &[(u64, RunState)],
+    ) -> Self {
+        self.config.add_trigger_region(name, address, outcomes);
+        self
+    }
+
+    /// Initialize the system registers of Cortex-M targets before each run
+    pub fn system_registers(mut self, system_registers: SystemRegisters) -> Self {
+        self.config.system_registers = Some(system_registers);
+        self
+    }
+
+    /// Add an address which must not be executed
+    pub fn guard_address(mut self, address: u64) -> Self {
+        self.config.guard_addresses.push(address);
+        self
+    }
+
+    /// Set values signaling a successful and a failed run on the auth address
+    pub fn markers(mut self, success_marker: u64, failure_marker: u64) -> Self {
+        self.config.success_marker = success_marker;
+        self.config.failure_marker = failure_marker;
+        self
+    }
+
+    /// Add a handler for a custom MMIO peripheral
+    pub fn mmio_handler<R, W>(mut self, base: u64, size: usize, read_fn: R, write_fn: W) -> Self
+    where
+        R: Fn(u64, usize) -> u64 + Send + Sync + 'static,
+        W: Fn(u64, usize, u64) + Send + Sync + 'static,
+    {
+        self.config.add_mmio_handler(base, size, read_fn, write_fn);
+        self
+    }
+
+    /// Check settings and build only the simulation configuration, e.g. for `Control::new`
+    ///
+    /// The elf file is not loaded, so target architecture and byte order default to
+    /// little endian Cortex-M. Use `build` for a ready simulation of the elf file
+    pub fn build_config(mut self) -> Result<SimulationConfig, String> {
+        self.config.target_arch = self.target_arch.unwrap_or_default();
+        self.config.endianness = self.endianness.unwrap_or_default();
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_rejects_overlapping_mmio() {
+        let layout = MemoryLayout::default();
+        assert!(SimulationBuilder::new()
+            .mmio_handler(0x4000_0000, 0x1000, |_, _| 0, |_, _, _| {})
+            .build_config()
+            .is_ok());
+        assert!(SimulationBuilder::new()
+            .mmio_handler(layout.auth_base + 0x800, 0x1000, |_, _| 0, |_, _, _| {})
+            .build_config()
+            .is_err());
+        assert!(SimulationBuilder::new()
+            .fault_instruction_limit(0)
+            .build_config()
+            .is_err());
+    }
+
+    #[test]
+    fn builder_checks_instruction_limits() {
+        assert!(SimulationBuilder::new()
+            .instruction_limit(0)
+            .build_config()
+            .is_err());
+        assert!(SimulationBuilder::new()
+            .instruction_limit(1000)
+            .fault_instruction_limit(1000)
+            .build_config()
+            .is_ok());
+        assert!(SimulationBuilder::new()
+            .instruction_limit(1000)
+            .fault_instruction_limit(1500)
+            .build_config()
+            .is_err());
+        // Simulation is built without elf file
+        assert!(SimulationBuilder::new().build().is_err());
+    }
+
+    #[test]
+    fn builder_checks_serial_region() {
+        let layout = MemoryLayout {
+            io_base: 0x4000_0000,
+            io_size: 0x2000,
+            ..MemoryLayout::default()
+        };
+        assert!(SimulationBuilder::new()
+            .memory_layout(layout)
+            .build_config()
+            .is_ok());
+        assert!(SimulationBuilder::new()
+            .memory_layout(layout)
+            .mmio_handler(0x4000_1000, 0x1000, |_, _| 0, |_, _, _| {})
+            .build_config()
+            .is_err());
+        assert!(SimulationBuilder::new()
+            .memory_layout(MemoryLayout {
+                io_size: 0x800,
+                ..layout
+            })
+            .build_config()
+            .is_err());
+    }
+
+    #[test]
+    fn builder_checks_trigger_regions() {
+        let layout = MemoryLayout::default();
+        let outcomes = [(0x1, RunState::Success), (0x2, RunState::Failed)];
+        assert!(SimulationBuilder::new()
+            .trigger_region("secure_boot", 0x3000_0000, &outcomes)
+            .trigger_region("debug_unlock", 0x3000_0004, &outcomes)
+            .build_config()
+            .is_ok());
+        assert!(SimulationBuilder::new()
+            .trigger_region("secure_boot", 0x3000_0000, &outcomes)
+            .trigger_region("secure_boot", 0x3000_0004, &outcomes)
+            .build_config()
+            .is_err());
+        assert!(SimulationBuilder::new()
+            .trigger_region("debug_unlock", layout.auth_base, &outcomes)
+            .build_config()
+            .is_err());
+        assert!(SimulationBuilder::new()
+            .trigger_region("rollback", 0x3000_0000, &[])
+            .build_config()
+            .is_err());
+    }
+}
@@ -1,7 +1,11 @@
-use super::{CpuState, RunState, TraceRecord, ARM_REG};
+use super::{
+    instruction_cycles, thumb_state, CpuState, Endianness, InvalidAccess, RunState, StopReason,
+    TargetArch, TraceRecord, WatchRecord, WriteRecord, ARM_REG, MINIMUM_MEMORY_SIZE,
+    STACK_GUARD_SIZE,
+};
 
-use unicorn_engine::unicorn_const::MemType;
-use unicorn_engine::Unicorn;
+use unicorn_engine::unicorn_const::{uc_error, MemType, Permission};
+use unicorn_engine::{RegisterARM, RegisterARM64, Unicorn};
 
 use log::debug;
 
@@ -15,27 +19,289 @@ pub fn mmio_auth_write_callback(
     _size: usize,
     value: i64,
 ) -> bool {
-    match value {
-        0x11111111 => {
-            emu.get_data_mut().state = RunState::Success;
-            debug!("Indicator: __SET_SIM_SUCCESS()")
+    let config = emu.get_data().config;
+    let (state, stop_reason) = match value as u64 {
+        value if value == config.success_marker => {
+            debug!("Indicator: __SET_SIM_SUCCESS()");
+            (RunState::Success, StopReason::AuthSuccess)
         }
-        0x22222222 => {
-            emu.get_data_mut().state = RunState::Failed;
-            debug!("Indicator: __SET_SIM_FAILED()")
+        value if value == config.failure_marker => {
+            debug!("Indicator: __SET_SIM_FAILED()");
+            (RunState::Failed, StopReason::AuthFailure)
         }
         _ => {
-            emu.get_data_mut().state = RunState::Error;
-            debug!("Indicator: Wrong_Value")
+            debug!("Indicator: Wrong_Value");
+            (RunState::Error, StopReason::AuthInvalid)
         }
+    };
+    set_trigger_state(emu, "auth", state, stop_reason);
+    true
+}
+
+/// Callback for write access to a user defined trigger address
+///
+/// The written value is mapped to a run state by the sentinel values of the trigger
+pub fn trigger_write_callback(emu: &mut Unicorn<CpuState>, index: usize, value: i64) -> bool {
+    let config = emu.get_data().config;
+    let trigger = &config.trigger_regions[index];
+    let (state, stop_reason) = match trigger
+        .outcomes
+        .iter()
+        .find(|(sentinel, _)| *sentinel == value as u64)
+    {
+        Some((_, RunState::Success)) => (RunState::Success, StopReason::AuthSuccess),
+        Some((_, RunState::Failed)) => (RunState::Failed, StopReason::AuthFailure),
+        Some((_, state)) => (*state, StopReason::AuthInvalid),
+        None => (RunState::Error, StopReason::AuthInvalid),
+    };
+    debug!("Trigger {}: 0x{:X} -> {:?}", trigger.name, value, state);
+    set_trigger_state(emu, &trigger.name, state, stop_reason);
+    true
+}
+
+/// Set state of a decision written to the auth or a trigger address
+///
+/// The first decision, its trigger and the writing instruction are kept if execution continues
+fn set_trigger_state<'a>(
+    emu: &mut Unicorn<CpuState<'a>>,
+    trigger: &'a str,
+    state: RunState,
+    stop_reason: StopReason,
+) {
+    let continue_after_auth = emu.get_data().config.continue_after_auth;
+    let pc = emu.pc_read().unwrap();
+    set_state(emu, state);
+    let cpu_state = emu.get_data_mut();
+    if cpu_state.auth_result.is_none() {
+        cpu_state.auth_result = Some(state);
+        cpu_state.trigger = Some(trigger);
+        cpu_state.trigger_pc = Some(pc);
+    }
+    if !continue_after_auth {
+        cpu_state.stop_reason = Some(stop_reason);
+        emu.emu_stop().expect("failed to stop");
+    }
+}
+
+/// Set run state and report a change to the state callback of the configuration
+fn set_state(emu: &mut Unicorn<CpuState>, state: RunState) {
+    if emu.get_data().state == state {
+        return;
     }
+    emu.get_data_mut().state = state;
+    let config = emu.get_data().config;
+    if let Some(state_callback) = &config.state_callback {
+        (state_callback.callback_fn)(state, emu.pc_read().unwrap());
+    }
+}
+
+/// Callback for invalid memory accesses
+///
+/// The access is recorded with the accessing instruction and the run is set to the
+/// error state. With `continue_after_invalid_access` unmapped memory is mapped and
+/// the execution continues, otherwise the access type is stored as stop reason
+pub fn hook_mem_invalid_callback(
+    emu: &mut Unicorn<CpuState>,
+    mem_type: MemType,
+    address: u64,
+    _size: usize,
+    _value: i64,
+) -> bool {
+    let pc = emu.pc_read().unwrap();
+    debug!(
+        "Invalid memory access {:?} at 0x{:X} (pc 0x{:X})",
+        mem_type, address, pc
+    );
+    emu.get_data_mut().invalid_accesses.push(InvalidAccess {
+        mem_type,
+        address,
+        pc,
+    });
+    set_state(emu, RunState::Error);
+
+    let stack_base = emu.get_data().stack_base;
+    // Unmapped write accesses directly below the stack are caused by a stack overflow
+    if mem_type == MemType::WRITE_UNMAPPED
+        && address < stack_base
+        && address + STACK_GUARD_SIZE >= stack_base
+        && stack_pointer(emu) < stack_base
+    {
+        emu.get_data_mut().stop_reason = Some(StopReason::StackOverflow);
+        return false;
+    }
+
+    let unmapped = matches!(
+        mem_type,
+        MemType::READ_UNMAPPED | MemType::WRITE_UNMAPPED | MemType::FETCH_UNMAPPED
+    );
+    // Protected memory can not be handled, the access is retried after the mapping.
+    // The page is unmapped again when the next run is initialized
+    let page = address & !(MINIMUM_MEMORY_SIZE as u64 - 1);
+    if unmapped
+        && emu.get_data().config.continue_after_invalid_access
+        && emu
+            .mem_map(page, MINIMUM_MEMORY_SIZE, Permission::ALL)
+            .is_ok()
+    {
+        emu.get_data_mut().mapped_pages.push(page);
+        return true;
+    }
+    emu.get_data_mut().stop_reason = Some(StopReason::MemoryFault(mem_type));
+    false
+}
+
+/// Hook for execution of a guard address
+///
+/// Reaching a guard address is handled like a successful attack
+pub fn hook_code_guard_callback(emu: &mut Unicorn<CpuState>, address: u64, _size: u32) {
+    debug!("Guard address 0x{:X} executed", address);
+    set_state(emu, RunState::Success);
+    emu.get_data_mut().stop_reason = Some(StopReason::GuardViolation(address));
+    emu.emu_stop().expect("failed to stop");
+}
+
+/// Callback for the target address of `run_until_address`
+pub fn hook_code_target_callback(emu: &mut Unicorn<CpuState>, address: u64, _size: u32) {
+    debug!("Target address 0x{:X} reached", address);
+    emu.get_data_mut().stop_reason = Some(StopReason::TargetAddress(address));
     emu.emu_stop().expect("failed to stop");
+}
+
+/// Exception number of an undefined instruction
+const EXCP_UDEF: u32 = 1;
+
+/// Callback for invalid instructions
+///
+/// The address of the instruction is stored in the stop reason
+pub fn hook_insn_invalid_callback(emu: &mut Unicorn<CpuState>) -> bool {
+    set_invalid_instruction(emu);
+    false
+}
+
+/// Callback for cpu exceptions
+///
+/// Undefined instructions are reported as invalid instruction, all other
+/// exceptions stop the emulation as they are not handled by the simulation
+pub fn hook_interrupt_callback(emu: &mut Unicorn<CpuState>, intno: u32) {
+    if intno == EXCP_UDEF {
+        set_invalid_instruction(emu);
+    } else {
+        debug!("Unhandled exception {}", intno);
+        emu.get_data_mut().stop_reason = Some(StopReason::Other(uc_error::EXCEPTION));
+    }
+    emu.emu_stop().expect("failed to stop");
+}
+
+/// Set error state with the address of the invalid instruction
+fn set_invalid_instruction(emu: &mut Unicorn<CpuState>) {
+    let address = emu.pc_read().unwrap();
+    debug!("Invalid instruction at 0x{:X}", address);
+    set_state(emu, RunState::Error);
+    emu.get_data_mut().stop_reason = Some(StopReason::InvalidInstruction(address));
+}
+
+/// Callback for write accesses to the guard area below the stack
+///
+/// The area may contain program data (e.g. .bss), so only writes with the
+/// stack pointer below the stack base signalize a stack overflow
+pub fn hook_stack_guard_callback(
+    emu: &mut Unicorn<CpuState>,
+    _mem_type: MemType,
+    address: u64,
+    _size: usize,
+    _value: i64,
+) -> bool {
+    if stack_pointer(emu) < emu.get_data().stack_base {
+        debug!("Stack overflow at 0x{:X}", address);
+        set_state(emu, RunState::Error);
+        emu.get_data_mut().stop_reason = Some(StopReason::StackOverflow);
+        emu.emu_stop().expect("failed to stop");
+    }
+    true
+}
+
+/// Callback for write accesses to a watched memory range
+///
+/// The hook is called before the memory is written, so the old content is still
+/// readable
+pub fn watchpoint_write_callback(
+    emu: &mut Unicorn<CpuState>,
+    address: u64,
+    size: usize,
+    value: i64,
+    halt: bool,
+) -> bool {
+    let old_data = emu.mem_read_as_vec(address, size).unwrap_or_default();
+    let new_data = match emu.get_data().config.endianness {
+        Endianness::Little => (value as u64).to_le_bytes()[..size].to_vec(),
+        Endianness::Big => (value as u64).to_be_bytes()[8 - size..].to_vec(),
+    };
+    let pc = emu.pc_read().unwrap();
+    debug!(
+        "Watchpoint: 0x{:X} written at 0x{:X} ({:02x?} -> {:02x?})",
+        address, pc, old_data, new_data
+    );
+
+    let changed = old_data != new_data;
+    emu.get_data_mut().watch_log.push(WatchRecord {
+        pc,
+        address,
+        old_data,
+        new_data,
+    });
+    if halt && changed {
+        emu.get_data_mut().stop_reason = Some(StopReason::Watchpoint(address));
+        emu.emu_stop().expect("failed to stop");
+    }
+    true
+}
+
+/// Read stack pointer of the target architecture
+fn stack_pointer(emu: &Unicorn<CpuState>) -> u64 {
+    match emu.get_data().config.target_arch {
+        TargetArch::CortexM | TargetArch::Arm => emu.reg_read(RegisterARM::SP),
+        TargetArch::AArch64 => emu.reg_read(RegisterARM64::SP),
+    }
+    .unwrap()
+}
+
+/// Callback for memory reads of faulted data
+///
+/// The mask of the pending data fault is XORed to the current memory before the
+/// read is executed, so the read already returns the modified data. Each fault
+/// is applied once
+pub fn hook_mem_read_data_fault_callback(
+    emu: &mut Unicorn<CpuState>,
+    _mem_type: MemType,
+    address: u64,
+    size: usize,
+    _value: i64,
+) -> bool {
+    let data_faults = &mut emu.get_data_mut().data_faults;
+    if let Some(index) = data_faults.iter().position(|(fault_address, xor_mask)| {
+        address < fault_address + xor_mask.len() as u64 && *fault_address < address + size as u64
+    }) {
+        let (fault_address, xor_mask) = data_faults.remove(index);
+        debug!(
+            "Data fault at 0x{:X} on read of 0x{:X}",
+            fault_address, address
+        );
+        let mut data = vec![0; xor_mask.len()];
+        emu.mem_read(fault_address, &mut data)
+            .expect("failed to read data fault");
+        data.iter_mut()
+            .zip(&xor_mask)
+            .for_each(|(byte, mask)| *byte ^= mask);
+        emu.mem_write(fault_address, &data)
+            .expect("failed to write data fault");
+        emu.get_data_mut().memory_modified = true;
+    }
     true
 }
 
 /// Callback for serial mem IO write access
 ///
-/// This IO write displays printed messages
+/// This IO write collects printed messages in the serial output buffer
 pub fn mmio_serial_write_callback(
     emu: &mut Unicorn<CpuState>,
     _address: u64,
@@ -43,7 +309,7 @@ pub fn mmio_serial_write_callback(
     value: u64,
 ) {
     if !emu.get_data().deactivate_print {
-        print!("{}", value as u8 as char);
+        emu.get_data_mut().serial_output.push(value as u8);
     }
 }
 
@@ -57,7 +323,16 @@ pub fn hook_code_decision_activation_callback(
     debug!("Call of decision_activation");
     // Set decision data according the run (negative/positive)
     let success: bool = !emu.get_data_mut().negative_run;
-    write_decision_element(emu, success);
+    match &emu.get_data().config.decision_handler {
+        Some(handler) => {
+            for (address, data) in (handler.handler_fn)(success) {
+                emu.mem_write(address, &data)
+                    .expect("failed to write decision data");
+            }
+            emu.get_data_mut().memory_modified = true;
+        }
+        None => write_decision_element(emu, success),
+    }
 }
 
 /// Code Hook for tracing functionality
@@ -68,7 +343,8 @@ pub fn hook_code_callback(emu: &mut Unicorn<CpuState>, address: u64, size: u32)
         let mut asm_instruction = vec![0x00; size as usize];
         emu.mem_read(address, &mut asm_instruction).unwrap();
 
-        let registers = if emu_data.with_register_data {
+        // Register data is only recorded for 32 bit ARM targets
+        let registers = if emu_data.with_register_data && emu_data.config.target_arch.is_aarch32() {
             let mut registers: [u32; 17] = [0; 17];
             ARM_REG.iter().enumerate().for_each(|(index, register)| {
                 registers[index] = emu.reg_read(*register).unwrap() as u32;
@@ -79,6 +355,7 @@ pub fn hook_code_callback(emu: &mut Unicorn<CpuState>, address: u64, size: u32)
         };
 
         let index = emu.get_data().trace_data.len();
+        let thumb = thumb_state(emu);
         // Record data
         emu.get_data_mut()
             .trace_data
@@ -86,11 +363,88 @@ pub fn hook_code_callback(emu: &mut Unicorn<CpuState>, address: u64, size: u32)
                 address,
                 index,
                 asm_instruction,
+                thumb,
                 registers,
             });
     }
 }
 
+/// Callback for counting the executed instructions
+pub fn hook_code_count_callback(emu: &mut Unicorn<CpuState>, _address: u64, _size: u32) {
+    emu.get_data_mut().instruction_count += 1;
+}
+
+/// Callback for counting the clock cycles of the executed instructions
+pub fn hook_code_cycle_callback(emu: &mut Unicorn<CpuState>, address: u64, size: u32) {
+    let config = emu.get_data().config;
+    if let Ok(instruction) = emu.mem_read_as_vec(address, size as usize) {
+        emu.get_data_mut().cycle_count += instruction_cycles(&instruction, config.target_arch);
+    }
+}
+
+/// Callback for the detection of endless loops
+///
+/// The run is stopped in the error state if no new address was executed within
+/// the configured number of instructions
+pub fn hook_code_loop_callback(emu: &mut Unicorn<CpuState>, address: u64, _size: u32) {
+    let limit = emu.get_data().config.loop_limit.unwrap_or(usize::MAX);
+    let cpu_state = emu.get_data_mut();
+    let count = cpu_state.execution_counts.entry(address).or_insert(0);
+    *count += 1;
+    if *count == 1 {
+        cpu_state.repeated_instructions = 0;
+        return;
+    }
+    cpu_state.repeated_instructions += 1;
+    if cpu_state.repeated_instructions >= limit {
+        debug!("Loop detected at 0x{:X}", address);
+        set_state(emu, RunState::Error);
+        emu.get_data_mut().stop_reason = Some(StopReason::LoopDetected(address));
+        emu.emu_stop().expect("failed to stop");
+    }
+}
+
+/// Keep only the written bytes of a write access value
+///
+/// Unicorn reports up to 8 bytes, larger or empty accesses are handled without overflow
+fn written_bytes(value: i64, size: usize) -> u64 {
+    match size {
+        0 => 0,
+        1..=7 => value as u64 & (u64::MAX >> (64 - 8 * size)),
+        _ => value as u64,
+    }
+}
+
+/// Callback for the log of all memory writes
+///
+/// Writes after the configured number of entries are not recorded
+pub fn hook_mem_write_log_callback(
+    emu: &mut Unicorn<CpuState>,
+    _mem_type: MemType,
+    address: u64,
+    size: usize,
+    value: i64,
+) -> bool {
+    let limit = emu.get_data().config.write_log_limit.unwrap_or(0);
+    if emu.get_data().write_log.len() < limit {
+        let pc = emu.pc_read().unwrap();
+        emu.get_data_mut().write_log.push(WriteRecord {
+            pc,
+            address,
+            size,
+            value: written_bytes(value, size),
+        });
+    }
+    true
+}
+
+/// Callback for recording the lowest stack pointer
+pub fn hook_code_stack_callback(emu: &mut Unicorn<CpuState>, _address: u64, _size: u32) {
+    let sp = stack_pointer(emu);
+    let min_stack_pointer = &mut emu.get_data_mut().min_stack_pointer;
+    *min_stack_pointer = Some(min_stack_pointer.map_or(sp, |min| min.min(sp)));
+}
+
 /// Write data to the decision data element according to given bool value
 /// true: success data will be copied to decision data element
 /// false: false data will be copied to decision data element
@@ -128,3 +482,17 @@ pub fn write_decision_element(emu: &mut Unicorn<CpuState>, success: bool) {
     emu.mem_write(decision_data_address, &data)
         .expect("failed to write to decision data element");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_bytes_of_access_size() {
+        assert_eq!(written_bytes(-1, 0), 0);
+        assert_eq!(written_bytes(-1, 1), 0xff);
+        assert_eq!(written_bytes(0x1234_5678, 2), 0x5678);
+        assert_eq!(written_bytes(-1, 8), u64::MAX);
+        assert_eq!(written_bytes(-1, 16), u64::MAX);
+    }
+}
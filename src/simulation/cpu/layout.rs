@@ -0,0 +1,29 @@
+/// Memory layout of the simulated target
+///
+/// The default values match the linker script of the provided victim programs.
+/// Stack values left at `None` are taken from the `.stack` section of the elf file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryLayout {
+    /// Start address of the stack
+    pub stack_base: Option<u64>,
+    /// Size of the stack in bytes
+    pub stack_size: Option<u64>,
+    /// Address of the success / failure indicator (`__SET_SIM_SUCCESS()`, `__SET_SIM_FAILED()`)
+    pub auth_base: u64,
+    /// Address of the serial output
+    pub io_base: u64,
+    /// Size of the serial output region in bytes (multiple of 4 KiB)
+    pub io_size: usize,
+}
+
+impl Default for MemoryLayout {
+    fn default() -> Self {
+        Self {
+            stack_base: None,
+            stack_size: None,
+            auth_base: 0xAA01000,
+            io_base: 0x11000000,
+            io_size: 0x1000,
+        }
+    }
+}
@@ -1,26 +1,52 @@
 use crate::elf_file::{ElfFile, PF_R, PF_W, PF_X};
 use crate::simulation::{
+    config::SimulationConfig,
     fault_data::FaultData,
-    record::{FaultRecord, TraceRecord},
+    record::{FaultRecord, TraceRecord, WatchRecord, WriteRecord},
 };
 
 mod callback;
+mod layout;
+mod target_arch;
+mod timing;
 
 use callback::{
-    hook_code_callback, hook_code_decision_activation_callback, mmio_auth_write_callback,
-    mmio_serial_write_callback,
+    hook_code_callback, hook_code_count_callback, hook_code_cycle_callback,
+    hook_code_decision_activation_callback, hook_code_guard_callback, hook_code_loop_callback,
+    hook_code_stack_callback, hook_code_target_callback, hook_insn_invalid_callback,
+    hook_interrupt_callback, hook_mem_invalid_callback, hook_mem_read_data_fault_callback,
+    hook_mem_write_log_callback, hook_stack_guard_callback, mmio_auth_write_callback,
+    mmio_serial_write_callback, trigger_write_callback, watchpoint_write_callback,
 };
 use unicorn_engine::unicorn_const::uc_error;
-use unicorn_engine::unicorn_const::{Arch, HookType, Mode, Permission, SECOND_SCALE};
-use unicorn_engine::{RegisterARM, Unicorn};
+use unicorn_engine::unicorn_const::{HookType, MemType, Permission, SECOND_SCALE};
+use unicorn_engine::{Context, RegisterARM, RegisterARM64, UcHookId, Unicorn};
 
-use log::debug;
-use std::collections::HashSet;
+use log::{debug, error, warn};
+use std::collections::{HashMap, HashSet};
+
+pub use layout::MemoryLayout;
+pub use target_arch::{Endianness, TargetArch};
+pub(crate) use timing::instruction_cycles;
 
 // Constant variable definitions
-const AUTH_BASE: u64 = 0xAA01000;
+pub(crate) const MINIMUM_MEMORY_SIZE: usize = 0x1000;
+/// Size of the guard area below the stack base to detect stack overflows
+const STACK_GUARD_SIZE: u64 = MINIMUM_MEMORY_SIZE as u64;
 
 const T1_RET: [u8; 2] = [0x70, 0x47]; // bx lr
+const A64_RET: [u8; 4] = [0xc0, 0x03, 0x5f, 0xd6]; // ret
+const A32_RET: [u8; 4] = [0x1e, 0xff, 0x2f, 0xe1]; // bx lr
+const T1_NOP: [u8; 2] = [0x00, 0xbf]; // nop
+const A64_NOP: [u8; 4] = [0x1f, 0x20, 0x03, 0xd5]; // nop
+const A32_NOP: [u8; 4] = [0x00, 0xf0, 0x20, 0xe3]; // nop
+
+/// Vector table offset register of the Cortex-M system control block
+const SCB_VTOR: u64 = 0xE000_ED08;
+/// Initial CPSR of ARM targets (supervisor mode, interrupts masked)
+const CPSR_INIT: u64 = 0x1d3;
+/// Thumb state bit of CPSR
+const CPSR_T: u64 = 1 << 5;
 
 pub const ARM_REG: [RegisterARM; 17] = [
     RegisterARM::R0,
@@ -42,6 +68,213 @@ pub const ARM_REG: [RegisterARM; 17] = [
     RegisterARM::CPSR,
 ];
 
+pub const ARM64_REG: [RegisterARM64; 34] = [
+    RegisterARM64::X0,
+    RegisterARM64::X1,
+    RegisterARM64::X2,
+    RegisterARM64::X3,
+    RegisterARM64::X4,
+    RegisterARM64::X5,
+    RegisterARM64::X6,
+    RegisterARM64::X7,
+    RegisterARM64::X8,
+    RegisterARM64::X9,
+    RegisterARM64::X10,
+    RegisterARM64::X11,
+    RegisterARM64::X12,
+    RegisterARM64::X13,
+    RegisterARM64::X14,
+    RegisterARM64::X15,
+    RegisterARM64::X16,
+    RegisterARM64::X17,
+    RegisterARM64::X18,
+    RegisterARM64::X19,
+    RegisterARM64::X20,
+    RegisterARM64::X21,
+    RegisterARM64::X22,
+    RegisterARM64::X23,
+    RegisterARM64::X24,
+    RegisterARM64::X25,
+    RegisterARM64::X26,
+    RegisterARM64::X27,
+    RegisterARM64::X28,
+    RegisterARM64::X29,
+    RegisterARM64::X30,
+    RegisterARM64::SP,
+    RegisterARM64::PC,
+    RegisterARM64::NZCV,
+];
+
+/// Mark a code address as Thumb code
+///
+/// Bit 0 of the start address of the emulation selects the Thumb state
+pub(crate) fn thumb_addr(address: u64) -> u64 {
+    address | 1
+}
+
+/// Remove the Thumb bit of a symbol or code address
+///
+/// Symbols of Thumb functions have bit 0 set, the PC and the hooks use the
+/// address of the instruction
+pub(crate) fn clear_thumb(address: u64) -> u64 {
+    address & !1
+}
+
+/// Align the end address of the code to an instruction boundary
+///
+/// The end address is taken from the file size of the code segment, the memory size
+/// can contain uninitialized data. The size is not necessarily a multiple of the
+/// instruction size (e.g. constant data behind the code), but the emulation only stops
+/// if the PC equals the end address. Unicorn compares it with the PC, which never has
+/// bit 0 set, so the end address is rounded down and not marked as Thumb address
+/// (`| 1`) like the start address
+fn align_end_address(end_address: u64, thumb: bool) -> u64 {
+    if thumb {
+        clear_thumb(end_address)
+    } else {
+        end_address & !3
+    }
+}
+
+/// Get stack region (base, size) from memory layout
+///
+/// Values which are not set in the memory layout are taken from the `.stack` section.
+/// Without both an error is returned
+fn stack_region(file_data: &ElfFile, layout: MemoryLayout) -> Result<(u64, u64), uc_error> {
+    if let (Some(base), Some(size)) = (layout.stack_base, layout.stack_size) {
+        return Ok((base, size));
+    }
+
+    let Some(stack) = file_data.section_map.get(".stack") else {
+        error!("No .stack section found, stack has to be set in the memory layout");
+        return Err(uc_error::ARG);
+    };
+    Ok((
+        layout.stack_base.unwrap_or(stack.sh_addr),
+        layout.stack_size.unwrap_or(stack.sh_size),
+    ))
+}
+
+/// FNV-1a hash over the executed addresses of a trace and the final run state
+///
+/// The hash depends on the order of the addresses
+fn trace_fingerprint(records: &[TraceRecord], state: RunState) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    records
+        .iter()
+        .filter_map(|record| match record {
+            TraceRecord::Instruction { address, .. } => Some(*address),
+            TraceRecord::Fault { .. } => None,
+        })
+        .flat_map(u64::to_le_bytes)
+        .chain([state as u8])
+        .fold(FNV_OFFSET, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        })
+}
+
+/// Get nop encoding which replaces an instruction of the given size
+///
+/// 32 bit Thumb instructions are replaced by two 16 bit nops. ARM and AArch64
+/// instructions have a fixed size of 4 bytes. The encoding is little endian for
+/// all byte orders as instructions are fetched little endian
+fn nop_cmd(target_arch: TargetArch, thumb: bool, size: usize) -> Result<Vec<u8>, String> {
+    let nop = match (thumb, target_arch, size) {
+        (true, _, 2 | 4) => T1_NOP.to_vec(),
+        (false, TargetArch::AArch64, 4) => A64_NOP.to_vec(),
+        (false, _, 4) => A32_NOP.to_vec(),
+        _ => {
+            return Err(format!(
+                "No {} byte nop available for {:?} (thumb: {})",
+                size, target_arch, thumb
+            ))
+        }
+    };
+    Ok(nop.repeat(size / nop.len()))
+}
+
+/// Check if the emulation is in Thumb state
+///
+/// ARM targets switch between ARM and Thumb code, so the state is taken from CPSR
+fn thumb_state(emu: &Unicorn<CpuState>) -> bool {
+    match emu.get_data().config.target_arch {
+        TargetArch::CortexM => true,
+        TargetArch::AArch64 => false,
+        TargetArch::Arm => emu.reg_read(RegisterARM::CPSR).unwrap() & CPSR_T != 0,
+    }
+}
+
+/// Get size of a Thumb command from its first halfword
+///
+/// Instructions are fetched little endian on Cortex-M and BE8 targets, so the
+/// upper byte of the halfword is always located at the second address
+fn thumb_cmd_size(data: [u8; 2]) -> usize {
+    let upper_byte = data[1];
+    // Check for 32bit cmd (0b11101... 0b1111....)
+    if (upper_byte & 0xF8 == 0xE8) || (upper_byte & 0xF0 == 0xF0) {
+        return 4;
+    }
+    2
+}
+
+/// Reason why an execution of the emulation stopped
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum StopReason {
+    /// Maximum number of instructions (or the timeout) reached
+    InstructionLimit,
+    /// Program reached the end address
+    EndAddress,
+    /// Success value written to the auth address
+    AuthSuccess,
+    /// Failure value written to the auth address
+    AuthFailure,
+    /// Unknown value written to the auth address
+    AuthInvalid,
+    /// Write access below the stack base
+    StackOverflow,
+    /// Undefined instruction at the given address
+    InvalidInstruction(u64),
+    /// Guard address was executed
+    GuardViolation(u64),
+    /// Target address of `run_until_address` reached
+    TargetAddress(u64),
+    /// Content of a halting watchpoint changed at the given address
+    Watchpoint(u64),
+    /// No new address was executed within the loop limit, stopped at the given address
+    LoopDetected(u64),
+    /// Access to unmapped or protected memory
+    MemoryFault(MemType),
+    /// Other emulation error
+    Other(uc_error),
+}
+
+impl StopReason {
+    /// Check if the execution was stopped by an emulation error
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            StopReason::StackOverflow
+                | StopReason::InvalidInstruction(_)
+                | StopReason::MemoryFault(_)
+                | StopReason::LoopDetected(_)
+                | StopReason::Other(_)
+        )
+    }
+}
+
+/// Invalid memory access of an execution
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct InvalidAccess {
+    /// Type of the access, e.g. `MemType::FETCH_UNMAPPED`
+    pub mem_type: MemType,
+    /// Accessed address
+    pub address: u64,
+    /// Address of the accessing instruction
+    pub pc: u64,
+}
+
 #[derive(PartialEq, Debug, Clone, Copy, Default)]
 pub enum RunState {
     #[default]
@@ -51,87 +284,417 @@ pub enum RunState {
     Error,
 }
 
+/// Saved state of a simulation (cpu context, program and stack memory)
+pub struct Snapshot {
+    context: Context,
+    memory: Vec<(u64, Vec<u8>)>,
+    program_counter: u64,
+    state: RunState,
+}
+
 pub struct Cpu<'a> {
     emu: Unicorn<'a, CpuState<'a>>,
     program_counter: u64,
+    hooks: Vec<UcHookId>,
+    /// Hooks of data faults, released when the next run is initialized
+    data_fault_hooks: Vec<(u64, UcHookId)>,
+    trace_hook_active: bool,
+    /// Read-only code is loaded and not modified since, so it is kept for the next run
+    code_loaded: bool,
+    /// Stack region (base, size) of the elf file and the memory layout
+    stack_region: (u64, u64),
 }
 
-struct CpuState<'a> {
+/// Internal state of an emulation which is shared with the unicorn callbacks
+pub struct CpuState<'a> {
     state: RunState,
     start_trace: bool,
     with_register_data: bool,
     negative_run: bool,
     deactivate_print: bool,
+    serial_output: Vec<u8>,
+    auth_result: Option<RunState>,
+    trigger: Option<&'a str>,
+    trigger_pc: Option<u64>,
+    stop_reason: Option<StopReason>,
+    instruction_count: usize,
+    min_stack_pointer: Option<u64>,
+    cycle_count: usize,
+    /// Number of executions of each address (loop detection)
+    execution_counts: HashMap<u64, usize>,
+    /// Instructions executed since the last new address (loop detection)
+    repeated_instructions: usize,
+    stack_base: u64,
+    data_faults: Vec<(u64, Vec<u8>)>,
     trace_data: Vec<TraceRecord>,
     fault_data: Vec<FaultData>,
+    watch_log: Vec<WatchRecord>,
+    write_log: Vec<WriteRecord>,
+    invalid_accesses: Vec<InvalidAccess>,
+    /// Pages mapped on invalid accesses which are unmapped with the next run
+    mapped_pages: Vec<u64>,
+    /// Memory was written by a callback, so read-only code has to be loaded again
+    memory_modified: bool,
     file_data: &'a ElfFile,
+    config: &'a SimulationConfig,
 }
 
 impl<'a> Cpu<'a> {
-    pub fn new(file_data: &'a ElfFile) -> Self {
-        // Setup platform -> ARMv8-m.base
+    pub fn new(file_data: &'a ElfFile, config: &'a SimulationConfig) -> Result<Self, uc_error> {
+        let stack_region = stack_region(file_data, config.memory_layout)?;
+        // Setup platform -> ARMv8-m.base or ARMv8-a
+        let (arch, mode) = config.target_arch.unicorn_arch_mode(config.endianness);
         let emu = Unicorn::new_with_data(
-            Arch::ARM,
-            Mode::LITTLE_ENDIAN | Mode::MCLASS,
+            arch,
+            mode,
             CpuState {
                 state: Default::default(),
                 start_trace: false,
                 with_register_data: false,
                 negative_run: false,
                 deactivate_print: false,
+                serial_output: Vec::new(),
+                auth_result: None,
+                trigger: None,
+                trigger_pc: None,
+                stop_reason: None,
+                instruction_count: 0,
+                min_stack_pointer: None,
+                cycle_count: 0,
+                execution_counts: HashMap::new(),
+                repeated_instructions: 0,
+                stack_base: 0,
+                data_faults: Vec::new(),
                 trace_data: Vec::new(),
                 fault_data: Vec::new(),
+                watch_log: Vec::new(),
+                write_log: Vec::new(),
+                invalid_accesses: Vec::new(),
+                mapped_pages: Vec::new(),
+                memory_modified: false,
                 file_data,
+                config,
             },
-        )
-        .expect("failed to initialize Unicorn instance");
+        )?;
 
-        debug!("Setup new unicorn instance");
+        debug!(
+            "Setup new unicorn instance for {:?} ({:?} endian)",
+            config.target_arch, config.endianness
+        );
 
-        Self {
+        Ok(Self {
             emu,
             program_counter: 0,
+            hooks: Vec::new(),
+            data_fault_hooks: Vec::new(),
+            trace_hook_active: false,
+            code_loaded: false,
+            stack_region,
+        })
+    }
+
+    /// Remove all registered hooks
+    ///
+    /// All hooks are tried to be removed, the first error is returned
+    fn remove_hooks(&mut self) -> Result<(), uc_error> {
+        let mut result = Ok(());
+        let data_fault_hooks = self.data_fault_hooks.drain(..).map(|(_, hook)| hook);
+        for hook in self.hooks.drain(..).chain(data_fault_hooks) {
+            if let Err(error) = self.emu.remove_hook(hook) {
+                if result.is_ok() {
+                    result = Err(error);
+                }
+            }
         }
+        result
+    }
+
+    /// Reuse the unicorn instance for a new elf file
+    ///
+    /// All hooks are removed and all memory regions are unmapped before the
+    /// memory mapping and breakpoints are setup for the new file
+    pub fn reload(&mut self, file_data: &'a ElfFile) -> Result<(), uc_error> {
+        self.stack_region = stack_region(file_data, self.get_memory_layout())?;
+        // Remove hooks of the previous file
+        self.remove_hooks()?;
+        self.trace_hook_active = false;
+        self.code_loaded = false;
+        // Unmap all memory regions
+        for region in self.emu.mem_regions()? {
+            self.emu
+                .mem_unmap(region.begin, (region.end - region.begin + 1) as usize)?;
+        }
+
+        // Reset internal state
+        let cpu_state = self.emu.get_data_mut();
+        cpu_state.state = RunState::Init;
+        cpu_state.start_trace = false;
+        cpu_state.with_register_data = false;
+        cpu_state.negative_run = false;
+        cpu_state.deactivate_print = false;
+        cpu_state.stop_reason = None;
+        cpu_state.instruction_count = 0;
+        cpu_state.min_stack_pointer = None;
+        cpu_state.cycle_count = 0;
+        cpu_state.execution_counts.clear();
+        cpu_state.repeated_instructions = 0;
+        cpu_state.auth_result = None;
+        cpu_state.trigger = None;
+        cpu_state.trigger_pc = None;
+        cpu_state.serial_output.clear();
+        cpu_state.data_faults.clear();
+        cpu_state.trace_data.clear();
+        cpu_state.fault_data.clear();
+        cpu_state.watch_log.clear();
+        cpu_state.write_log.clear();
+        cpu_state.invalid_accesses.clear();
+        cpu_state.mapped_pages.clear();
+        cpu_state.memory_modified = false;
+        cpu_state.file_data = file_data;
+        self.program_counter = 0;
+
+        self.setup_mmio()?;
+        self.setup_breakpoints()
+    }
+
+    /// Get the unicorn instance for features which are not wrapped by the simulation
+    ///
+    /// Hooks, memory mappings and state registered by the simulation are not
+    /// tracked for changes done here. Keeping them consistent (e.g. removing own
+    /// hooks before the next run) is the responsibility of the caller
+    #[cfg(feature = "advanced")]
+    pub fn unicorn_mut(&mut self) -> &mut Unicorn<'a, CpuState<'a>> {
+        // The code may be modified by the caller
+        self.code_loaded = false;
+        &mut self.emu
+    }
+
+    /// Get all mapped memory regions (start, size, permission) including MMIO regions
+    pub fn memory_regions(&self) -> Result<Vec<(u64, usize, Permission)>, uc_error> {
+        Ok(self
+            .emu
+            .mem_regions()?
+            .iter()
+            .map(|region| {
+                (
+                    region.begin,
+                    (region.end - region.begin + 1) as usize,
+                    region.perms,
+                )
+            })
+            .collect())
+    }
+
+    /// Get configuration of the simulation
+    pub fn get_config(&self) -> &'a SimulationConfig {
+        self.emu.get_data().config
+    }
+
+    /// Get target architecture of the simulation
+    pub fn get_target_arch(&self) -> TargetArch {
+        self.emu.get_data().config.target_arch
+    }
+
+    /// Get byte order of the simulation
+    pub fn get_endianness(&self) -> Endianness {
+        self.emu.get_data().config.endianness
+    }
+
+    /// Get memory layout of the simulation
+    fn get_memory_layout(&self) -> MemoryLayout {
+        self.emu.get_data().config.memory_layout
     }
 
     /// Initialize all required register to zero
     ///
     /// Additionally the SP is set to start of stack
     pub fn init_register(&mut self) {
-        // Clear registers
-        ARM_REG
-            .iter()
-            .for_each(|reg| self.emu.reg_write(*reg, 0x00).unwrap());
-
-        // Setup stack pointer
-        let stack = self
-            .emu
-            .get_data()
-            .file_data
-            .section_map
-            .get(".stack")
-            .expect("Failed to get stack section");
+        let (stack_base, stack_size) = self.get_stack_region();
+        match self.get_target_arch() {
+            TargetArch::CortexM => {
+                // Clear registers
+                ARM_REG
+                    .iter()
+                    .for_each(|reg| self.emu.reg_write(*reg, 0x00).unwrap());
+                // CONTROL selects the stack pointer, so it is set before the stack
+                self.init_system_registers();
+                // Setup stack pointer
+                self.emu
+                    .reg_write(RegisterARM::SP, stack_base + stack_size)
+                    .expect("failed to set register");
+            }
+            TargetArch::AArch64 => {
+                // Clear registers
+                ARM64_REG
+                    .iter()
+                    .for_each(|reg| self.emu.reg_write(*reg, 0x00).unwrap());
+                // Setup stack pointer
+                self.emu
+                    .reg_write(RegisterARM64::SP, stack_base + stack_size)
+                    .expect("failed to set register");
+            }
+            TargetArch::Arm => {
+                // Clear registers except of the status register
+                ARM_REG
+                    .iter()
+                    .filter(|reg| **reg != RegisterARM::CPSR)
+                    .for_each(|reg| self.emu.reg_write(*reg, 0x00).unwrap());
+                // Setup stack pointer
+                self.emu
+                    .reg_write(RegisterARM::SP, stack_base + stack_size)
+                    .expect("failed to set register");
+                // Start in the instruction set of the entry point (bit 0 set for Thumb)
+                let mut cpsr = CPSR_INIT;
+                if self.emu.get_data().file_data.entry_point & 1 != 0 {
+                    cpsr |= CPSR_T;
+                }
+                self.emu
+                    .reg_write(RegisterARM::CPSR, cpsr)
+                    .expect("failed to set register");
+            }
+        }
+    }
 
+    /// Set the Cortex-M system registers of the configuration
+    fn init_system_registers(&mut self) {
+        let system_registers = match self.emu.get_data().config.system_registers {
+            Some(system_registers) => system_registers,
+            None => return,
+        };
         self.emu
-            .reg_write(RegisterARM::SP, stack.sh_addr + stack.sh_size)
+            .reg_write(RegisterARM::CONTROL, system_registers.control as u64)
             .expect("failed to set register");
+        self.emu
+            .reg_write(RegisterARM::PRIMASK, system_registers.primask as u64)
+            .expect("failed to set register");
+
+        let vtor = system_registers.vtor.unwrap_or_else(|| {
+            self.code_ranges()
+                .first()
+                .map_or(0, |(begin, _)| *begin as u32)
+        });
+        let data = match self.get_endianness() {
+            Endianness::Little => vtor.to_le_bytes(),
+            Endianness::Big => vtor.to_be_bytes(),
+        };
+        self.emu
+            .mem_write(SCB_VTOR, &data)
+            .expect("failed to set VTOR");
+    }
+
+    /// Check if the cpu is in Thumb state
+    ///
+    /// ARM targets switch between ARM and Thumb code, so the state is taken from CPSR
+    pub fn is_thumb_state(&self) -> bool {
+        thumb_state(&self.emu)
+    }
+
+    /// Get stack region (base, size) of the simulation
+    fn get_stack_region(&self) -> (u64, u64) {
+        self.stack_region
+    }
+
+    /// Check if the stack is located inside of one of the loaded segments
+    fn stack_in_segments(&self) -> bool {
+        let (stack_base, stack_size) = self.get_stack_region();
+        self.emu
+            .get_data()
+            .file_data
+            .program_data
+            .iter()
+            .any(|segment| {
+                stack_base >= segment.0.p_paddr
+                    && stack_base + stack_size <= segment.0.p_paddr + segment.0.p_memsz
+            })
     }
 
     /// Load source code from elf file into simulation
     ///
-    /// The PC is set to the start of the program
+    /// The PC is set to the start of the program. Segments which are read-only
+    /// for the emulated code are only written again if they were modified by
+    /// the simulation (e.g. by faults) since the last load
     pub fn load_code(&mut self) {
+        // Writes of callbacks (e.g. data faults) bypass `memory_write`
+        if std::mem::take(&mut self.emu.get_data_mut().memory_modified) {
+            self.code_loaded = false;
+        }
         let program_parts = &self.emu.get_data().file_data.program_data;
+        let fill_pattern = self.emu.get_data().config.memory_fill_pattern;
+        let read_only = self.read_only_regions();
 
         // Iterate over all program parts and write them to memory
         for part in program_parts {
+            let end = part.0.p_paddr + part.0.p_memsz;
+            if self.code_loaded
+                && read_only
+                    .iter()
+                    .any(|(begin, last)| part.0.p_paddr >= *begin && end <= *last + 1)
+            {
+                continue;
+            }
             self.emu
                 .mem_write(part.0.p_paddr, &part.1)
                 .expect("failed to write program data");
+            // Clear remaining part of the segment (e.g. .bss) to start with identical memory
+            let remaining = part.0.p_memsz.saturating_sub(part.1.len() as u64);
+            if remaining != 0 {
+                let data = match fill_pattern {
+                    Some(pattern) => self.fill_data(pattern, remaining as usize),
+                    None => vec![0; remaining as usize],
+                };
+                self.emu
+                    .mem_write(part.0.p_paddr + part.1.len() as u64, &data)
+                    .expect("failed to clear program data");
+            }
+        }
+
+        // Stack may be located outside of the program segments, so it is
+        // initialized separately to start each run with identical memory
+        let (stack_base, stack_size) = self.get_stack_region();
+        let stack_data = match fill_pattern {
+            Some(pattern) => Some(self.fill_data(pattern, stack_size as usize)),
+            None if !self.stack_in_segments() => Some(vec![0; stack_size as usize]),
+            None => None,
+        };
+        if let Some(data) = stack_data {
+            self.emu
+                .mem_write(stack_base, &data)
+                .expect("failed to fill stack");
+        }
+
+        if fill_pattern.is_some() {
+            // Uninitialized variables are expected to be zero
+            if let Some(bss) = self.emu.get_data().file_data.section_map.get(".bss") {
+                let (address, size) = (bss.sh_addr, bss.sh_size as usize);
+                self.emu
+                    .mem_write(address, &vec![0; size])
+                    .expect("failed to clear .bss");
+            }
         }
 
         // set initial program start address
-        self.program_counter = self.emu.get_data().file_data.header.e_entry;
+        self.program_counter = self.emu.get_data().file_data.entry_point;
+        self.code_loaded = true;
+    }
+
+    /// Get memory regions (begin, end inclusive) which can't be written by the emulated code
+    fn read_only_regions(&self) -> Vec<(u64, u64)> {
+        self.emu
+            .mem_regions()
+            .unwrap_or_default()
+            .iter()
+            .filter(|region| !region.perms.contains(Permission::WRITE))
+            .map(|region| (region.begin, region.end))
+            .collect()
+    }
+
+    /// Repeat the fill pattern for the given size in the byte order of the target
+    fn fill_data(&self, pattern: u32, size: usize) -> Vec<u8> {
+        let bytes = match self.get_endianness() {
+            Endianness::Little => pattern.to_le_bytes(),
+            Endianness::Big => pattern.to_be_bytes(),
+        };
+        bytes.iter().copied().cycle().take(size).collect()
     }
 
     /// Function to deactivate printf of c program to
@@ -147,8 +710,16 @@ impl<'a> Cpu<'a> {
             .get("serial_puts")
             .expect("No serial_puts symbol found");
 
+        // Instructions are little endian independent of the data byte order
+        let ret_cmd = match self.get_target_arch() {
+            TargetArch::CortexM => T1_RET.to_vec(),
+            TargetArch::AArch64 => A64_RET.to_vec(),
+            // Thumb functions are marked with bit 0 of the symbol address
+            TargetArch::Arm if serial_puts.st_value & 1 != 0 => T1_RET.to_vec(),
+            TargetArch::Arm => A32_RET.to_vec(),
+        };
         self.emu
-            .mem_write(serial_puts.st_value & 0xfffffffe, &T1_RET)
+            .mem_write(clear_thumb(serial_puts.st_value), &ret_cmd)
             .unwrap();
     }
 
@@ -156,41 +727,153 @@ impl<'a> Cpu<'a> {
     ///
     /// BreakPoints
     /// { binInfo.Symbols["decision_activation"].Address }
-    pub fn setup_breakpoints(&mut self) {
-        let decision_activation = self
+    /// The breakpoint is skipped if the symbol is not part of the elf file
+    pub fn setup_breakpoints(&mut self) -> Result<(), uc_error> {
+        let config = self.emu.get_data().config;
+        let symbol = match &config.decision_handler {
+            Some(handler) => handler.symbol.as_str(),
+            None => "decision_activation",
+        };
+        match self.emu.get_data().file_data.symbol_map.get(symbol) {
+            Some(decision_activation) => {
+                let address = clear_thumb(decision_activation.st_value);
+                let hook = self.emu.add_code_hook(
+                    address,
+                    address + 1,
+                    hook_code_decision_activation_callback,
+                )?;
+                self.hooks.push(hook);
+            }
+            None => warn!("No {} symbol found, decision breakpoint skipped", symbol),
+        }
+
+        let auth_base = self.get_memory_layout().auth_base;
+        let hook = self.emu.add_mem_hook(
+            HookType::MEM_WRITE,
+            auth_base,
+            auth_base + 4,
+            mmio_auth_write_callback,
+        )?;
+        self.hooks.push(hook);
+
+        // User defined triggers
+        for (index, trigger) in self
             .emu
             .get_data()
-            .file_data
-            .symbol_map
-            .get("decision_activation")
-            .expect("No decision_activation symbol found");
+            .config
+            .trigger_regions
+            .iter()
+            .enumerate()
+        {
+            let hook = self.emu.add_mem_hook(
+                HookType::MEM_WRITE,
+                trigger.address,
+                trigger.address + 3,
+                move |emu: &mut Unicorn<CpuState>, _, _, _, value| {
+                    trigger_write_callback(emu, index, value)
+                },
+            )?;
+            self.hooks.push(hook);
+        }
 
-        self.emu
-            .add_code_hook(
-                decision_activation.st_value,
-                decision_activation.st_value + 1,
-                hook_code_decision_activation_callback,
-            )
-            .expect("failed to set decision_activation code hook");
+        // Count executed instructions of the code
+        if self.emu.get_data().config.count_instructions {
+            for (begin, end) in self.code_ranges() {
+                let hook = self
+                    .emu
+                    .add_code_hook(begin, end, hook_code_count_callback)?;
+                self.hooks.push(hook);
+            }
+        }
 
-        self.emu
-            .add_mem_hook(
+        // Lowest stack pointer of the run
+        if self.emu.get_data().config.track_stack_usage {
+            for (begin, end) in self.code_ranges() {
+                let hook = self
+                    .emu
+                    .add_code_hook(begin, end, hook_code_stack_callback)?;
+                self.hooks.push(hook);
+            }
+        }
+
+        // Log of all memory writes
+        if self.emu.get_data().config.write_log_limit.is_some() {
+            let hook = self.emu.add_mem_hook(
                 HookType::MEM_WRITE,
-                AUTH_BASE,
-                AUTH_BASE + 4,
-                mmio_auth_write_callback,
-            )
-            .expect("failed to set memory hook");
+                0,
+                u64::MAX,
+                hook_mem_write_log_callback,
+            )?;
+            self.hooks.push(hook);
+        }
+
+        // Clock cycles of the run
+        if self.emu.get_data().config.count_cycles {
+            for (begin, end) in self.code_ranges() {
+                let hook = self
+                    .emu
+                    .add_code_hook(begin, end, hook_code_cycle_callback)?;
+                self.hooks.push(hook);
+            }
+        }
+
+        // Detection of endless loops
+        if self.emu.get_data().config.loop_limit.is_some() {
+            for (begin, end) in self.code_ranges() {
+                let hook = self
+                    .emu
+                    .add_code_hook(begin, end, hook_code_loop_callback)?;
+                self.hooks.push(hook);
+            }
+        }
+
+        // Addresses which must not be executed
+        for address in self.emu.get_data().config.guard_addresses.clone() {
+            let address = clear_thumb(address);
+            let hook = self
+                .emu
+                .add_code_hook(address, address, hook_code_guard_callback)?;
+            self.hooks.push(hook);
+        }
+
+        // Guard area below the stack to detect stack overflows
+        let (stack_base, _) = self.get_stack_region();
+        self.emu.get_data_mut().stack_base = stack_base;
+        if stack_base >= STACK_GUARD_SIZE {
+            let hook = self.emu.add_mem_hook(
+                HookType::MEM_WRITE,
+                stack_base - STACK_GUARD_SIZE,
+                stack_base - 1,
+                hook_stack_guard_callback,
+            )?;
+            self.hooks.push(hook);
+        }
+
+        let hook = self.emu.add_mem_hook(
+            HookType::MEM_INVALID,
+            0,
+            u64::MAX,
+            hook_mem_invalid_callback,
+        )?;
+        self.hooks.push(hook);
+
+        // Detect undefined instructions (e.g. caused by command bit flips)
+        let hook = self.emu.add_insn_invalid_hook(hook_insn_invalid_callback)?;
+        self.hooks.push(hook);
+        let hook = self.emu.add_intr_hook(hook_interrupt_callback)?;
+        self.hooks.push(hook);
+
+        Ok(())
     }
 
     /// Setup memory mapping, stack, io mapping
-    pub fn setup_mmio(&mut self) {
-        const MINIMUM_MEMORY_SIZE: usize = 0x1000;
-
+    pub fn setup_mmio(&mut self) -> Result<(), uc_error> {
         let segments = &self.emu.get_data().file_data.program_data;
+        let code_permission = self.emu.get_data().config.code_permission;
 
-        // Iterate over all program parts and write them to memory
-        for segment in segments {
+        // Collect memory regions of all program parts (start, end, permission)
+        let mut regions: Vec<(u64, u64, Permission)> = Vec::new();
+        for segment in segments.iter().filter(|segment| segment.0.p_memsz != 0) {
             let mut permission: Permission = Permission::NONE;
 
             // Convert p_flags to permission
@@ -203,82 +886,348 @@ impl<'a> Cpu<'a> {
             if segment.0.p_flags & PF_R != 0 {
                 permission |= Permission::READ;
             }
-            // Map segment to memory
+            // Configured permission of code segments
+            if segment.0.p_flags & PF_X != 0 {
+                permission = code_permission.unwrap_or(permission);
+            }
+            // Calculate region of part with a minimum granularity of 4KB
+            let start = segment.0.p_paddr & !(MINIMUM_MEMORY_SIZE as u64 - 1);
+            let size = ((segment.0.p_paddr - start + segment.0.p_memsz) as usize
+                + MINIMUM_MEMORY_SIZE)
+                & !(MINIMUM_MEMORY_SIZE - 1);
+            regions.push((start, start + size as u64, permission));
+        }
+
+        // Merge parts sharing the same 4KB pages (e.g. .data directly behind .text)
+        regions.sort_by_key(|region| region.0);
+        let mut merged: Vec<(u64, u64, Permission)> = Vec::new();
+        for (start, end, permission) in regions {
+            match merged.last_mut() {
+                Some(last) if start < last.1 => {
+                    last.1 = last.1.max(end);
+                    last.2 |= permission;
+                }
+                _ => merged.push((start, end, permission)),
+            }
+        }
+
+        // Map program parts to memory
+        for (start, end, permission) in merged {
             self.emu
-                .mem_map(
-                    segment.0.p_paddr,
-                    (segment.0.p_memsz as usize + MINIMUM_MEMORY_SIZE) & 0xfffff000, // Calculate length of part with a minimum granularity of 4KB
-                    permission,
-                )
-                .expect("failed to map code page");
+                .mem_map(start, (end - start) as usize, permission)?;
+        }
+
+        // Map stack separately if it is located outside of the loaded segments
+        let (stack_base, stack_size) = self.get_stack_region();
+        if !self.stack_in_segments() {
+            let start = stack_base & !(MINIMUM_MEMORY_SIZE as u64 - 1);
+            let end = (stack_base + stack_size + MINIMUM_MEMORY_SIZE as u64 - 1)
+                & !(MINIMUM_MEMORY_SIZE as u64 - 1);
+            self.emu.mem_map(
+                start,
+                (end - start) as usize,
+                Permission::READ | Permission::WRITE,
+            )?;
         }
 
         // Auth success / failed trigger
+        let layout = self.get_memory_layout();
         self.emu
-            .mem_map(AUTH_BASE, MINIMUM_MEMORY_SIZE, Permission::WRITE)
-            .expect("failed to map mmio replacement");
+            .mem_map(layout.auth_base, MINIMUM_MEMORY_SIZE, Permission::WRITE)?;
+
+        // Pages of user defined triggers which are not mapped yet
+        let config = self.emu.get_data().config;
+        for trigger in &config.trigger_regions {
+            let page = trigger.address & !(MINIMUM_MEMORY_SIZE as u64 - 1);
+            if !self
+                .emu
+                .mem_regions()?
+                .iter()
+                .any(|region| page >= region.begin && page <= region.end)
+            {
+                self.emu
+                    .mem_map(page, MINIMUM_MEMORY_SIZE, Permission::WRITE)?;
+            }
+        }
 
         // IO address space
         self.emu
-            .mmio_map_wo(0x11000000, MINIMUM_MEMORY_SIZE, mmio_serial_write_callback)
-            .expect("failed to map serial IO");
+            .mmio_map_wo(layout.io_base, layout.io_size, mmio_serial_write_callback)?;
+
+        // User defined peripherals
+        let config = self.emu.get_data().config;
+        for handler in &config.mmio_handlers {
+            let (read_fn, write_fn) = (&handler.read_fn, &handler.write_fn);
+            self.emu.mmio_map(
+                handler.base,
+                handler.size,
+                Some(move |_: &mut Unicorn<CpuState>, offset, size| read_fn(offset, size)),
+                Some(move |_: &mut Unicorn<CpuState>, offset, size, value| {
+                    write_fn(offset, size, value)
+                }),
+            )?;
+        }
+
+        // System control space for the initialization of VTOR
+        if config.system_registers.is_some() && config.target_arch == TargetArch::CortexM {
+            let page = SCB_VTOR & !(MINIMUM_MEMORY_SIZE as u64 - 1);
+            if !self
+                .emu
+                .mem_regions()?
+                .iter()
+                .any(|region| page >= region.begin && page <= region.end)
+            {
+                self.emu.mem_map(
+                    page,
+                    MINIMUM_MEMORY_SIZE,
+                    Permission::READ | Permission::WRITE,
+                )?;
+            }
+        }
+
+        Ok(())
     }
 
     /// Execute code on pc set in internal structure till cycles
     ///
+    /// `cycles` is the maximum number of executed instructions. The wall-clock
+    /// timeout is taken from the configuration (`timeout_secs`)
+    ///
     /// If debug is set to true, execution is done by single steps
-    pub fn run_steps(&mut self, cycles: usize, debug: bool) -> Result<(), uc_error> {
-        let mut ret_val;
+    pub fn run_steps(&mut self, cycles: usize, debug: bool) -> StopReason {
+        let stop_reason;
         if debug {
             let mut cyc = cycles;
-            ret_val = Ok(());
-            while ret_val == Ok(()) && cyc != 0 {
-                //println!("Executing address : 0x{:X}", self.emu.get_data().program_counter);
-                ret_val = self.run_steps(1, false);
+            let mut step_reason = StopReason::InstructionLimit;
+            while !step_reason.is_error() && cyc != 0 {
+                step_reason = self.run_steps(1, false);
                 cyc -= 1;
-                println!("PC : 0x{:X}", self.emu.pc_read().unwrap());
+                debug!("PC : 0x{:X}", self.emu.pc_read().unwrap());
                 if self.emu.get_data().state != RunState::Init {
-                    println!("Stopped on marker: {:?}", self.emu.get_data().state);
+                    debug!("Stopped on marker: {:?}", self.emu.get_data().state);
                     break;
                 }
             }
+            stop_reason = step_reason;
         } else {
-            let end_address = self.emu.get_data().file_data.program_data[0].0.p_paddr
-                + self.emu.get_data().file_data.program_data[0].0.p_memsz;
-
-            // Start from last PC
-            ret_val = self.emu.emu_start(
-                self.program_counter | 1,
-                end_address | 1,
-                SECOND_SCALE,
-                cycles,
+            let thumb = self.is_thumb_state();
+            let end_address = align_end_address(
+                self.emu.get_data().file_data.program_data[0].0.p_paddr
+                    + self.emu.get_data().file_data.program_data[0].0.p_filesz,
+                thumb,
             );
+
+            // Start from last PC. Bit 0 of the start address selects the Thumb state,
+            // the end address is compared with the PC and is not marked
+            let start_address = if thumb {
+                thumb_addr(self.program_counter)
+            } else {
+                self.program_counter
+            };
+            // Reset stop reason which is set by the callbacks
+            self.emu.get_data_mut().stop_reason = None;
+            // emu_start(begin, until, timeout in microseconds, instruction count)
+            let timeout = self.emu.get_data().config.timeout_secs * SECOND_SCALE;
+            let ret_val = self
+                .emu
+                .emu_start(start_address, end_address, timeout, cycles);
+
+            stop_reason = match (ret_val, self.emu.get_data().stop_reason) {
+                (_, Some(reason)) => reason,
+                (Err(error), None) => StopReason::Other(error),
+                (Ok(()), None) if self.emu.pc_read().unwrap() == end_address => {
+                    StopReason::EndAddress
+                }
+                (Ok(()), None) => StopReason::InstructionLimit,
+            };
+            self.emu.get_data_mut().stop_reason = Some(stop_reason);
+            self.release_applied_data_fault_hooks();
         }
         // Store new PC
         self.program_counter = self.emu.pc_read().unwrap();
 
-        ret_val
+        stop_reason
     }
 
-    pub fn get_asm_cmd_size(&self, address: u64) -> Option<usize> {
+    /// Execute until the program counter reaches the target address
+    ///
+    /// The instruction at the target address is not executed. Returns true if
+    /// the target was reached before `max_steps` instructions or another stop
+    pub fn run_until_address(&mut self, target: u64, max_steps: usize) -> Result<bool, uc_error> {
+        let target = clear_thumb(target);
+        let hook = self
+            .emu
+            .add_code_hook(target, target, hook_code_target_callback)?;
+        let stop_reason = self.run_steps(max_steps, false);
+        self.emu.remove_hook(hook)?;
+        Ok(stop_reason == StopReason::TargetAddress(target))
+    }
+
+    /// Get reason why the last execution stopped
+    pub fn get_stop_reason(&self) -> Option<StopReason> {
+        self.emu.get_data().stop_reason
+    }
+
+    /// Get size of the assembler command at the given address
+    ///
+    /// Returns the memory error if the address can not be read
+    pub fn get_asm_cmd_size(&self, address: u64) -> Result<usize, uc_error> {
         let mut data: [u8; 2] = [0; 2];
-        // Check for 32bit cmd (0b11101... 0b1111....)
-        if self.emu.mem_read(address, &mut data).is_ok() {
-            if (data[1] & 0xF8 == 0xE8) || (data[1] & 0xF0 == 0xF0) {
-                return Some(4);
-            }
-            return Some(2);
+        self.emu.mem_read(address, &mut data)?;
+        // AArch64 and ARM commands have a fixed size
+        if !self.is_thumb_state() {
+            return Ok(4);
+        }
+        Ok(thumb_cmd_size(data))
+    }
+
+    /// Get nop encoding for an instruction of the given size in the current state of the target
+    pub fn nop_cmd(&self, size: usize) -> Result<Vec<u8>, String> {
+        nop_cmd(self.get_target_arch(), self.is_thumb_state(), size)
+    }
+
+    /// Get address of the instruction `count` instructions behind the given address
+    ///
+    /// Sizes are taken from the loaded code, so mixed 2 and 4 byte Thumb
+    /// instructions are handled
+    pub fn instruction_address(&self, address: u64, count: usize) -> Result<u64, uc_error> {
+        let mut address = address;
+        for _ in 0..count {
+            address += self.get_asm_cmd_size(address)? as u64;
         }
-        None
+        Ok(address)
     }
 
     /// Initialize the internal program state
     pub fn init_states(&mut self, run_state: bool) {
+        // Faults of the previous run must not be active anymore
+        self.release_data_fault_hooks();
+        self.unmap_pages_of_invalid_accesses();
+
+        let cpu_state = self.emu.get_data_mut();
         // Set run type
-        self.emu.get_data_mut().negative_run = !run_state;
+        cpu_state.negative_run = !run_state;
 
         // Set global state to initilized
-        self.emu.get_data_mut().state = RunState::Init;
+        cpu_state.state = RunState::Init;
+
+        // Remove data and trace settings of previous runs
+        cpu_state.start_trace = false;
+        cpu_state.with_register_data = false;
+        cpu_state.data_faults.clear();
+        cpu_state.instruction_count = 0;
+        cpu_state.min_stack_pointer = None;
+        cpu_state.cycle_count = 0;
+        cpu_state.execution_counts.clear();
+        cpu_state.repeated_instructions = 0;
+        cpu_state.auth_result = None;
+        cpu_state.trigger = None;
+        cpu_state.trigger_pc = None;
+        cpu_state.serial_output.clear();
+        cpu_state.trace_data.clear();
+        cpu_state.fault_data.clear();
+        cpu_state.watch_log.clear();
+        cpu_state.write_log.clear();
+        cpu_state.invalid_accesses.clear();
+    }
+
+    /// Save cpu context and the memory of all program segments and the stack
+    pub fn snapshot(&self) -> Result<Snapshot, uc_error> {
+        let mut context = self.emu.context_alloc()?;
+        self.emu.context_save(&mut context)?;
+
+        let mut regions: Vec<(u64, u64)> = self
+            .emu
+            .get_data()
+            .file_data
+            .program_data
+            .iter()
+            .map(|segment| (segment.0.p_paddr, segment.0.p_memsz))
+            .collect();
+        regions.push(self.get_stack_region());
+
+        let mut memory = Vec::new();
+        for (address, size) in regions {
+            let mut data = vec![0; size as usize];
+            self.emu.mem_read(address, &mut data)?;
+            memory.push((address, data));
+        }
+
+        Ok(Snapshot {
+            context,
+            memory,
+            program_counter: self.program_counter,
+            state: self.get_state(),
+        })
+    }
+
+    /// Restore a previously saved snapshot
+    ///
+    /// Cached instructions of the restored memory are cleared
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), uc_error> {
+        self.code_loaded = false;
+        self.emu.context_restore(&snapshot.context)?;
+        for (address, data) in &snapshot.memory {
+            self.emu.mem_write(*address, data)?;
+            self.emu
+                .ctl_remove_cache(*address, *address + data.len() as u64)?;
+        }
+        self.program_counter = snapshot.program_counter;
+        self.emu.get_data_mut().state = snapshot.state;
+        Ok(())
+    }
+
+    /// Get state of the first write to the auth address of the current run
+    ///
+    /// With `continue_after_auth` this can differ from the final state
+    pub fn get_auth_result(&self) -> Option<RunState> {
+        self.emu.get_data().auth_result
+    }
+
+    /// Get name of the trigger of the first decision of the current run
+    ///
+    /// The auth address is named "auth"
+    pub fn get_trigger(&self) -> Option<&str> {
+        self.emu.get_data().trigger
+    }
+
+    /// Get address of the instruction which wrote the first decision of the current run
+    pub fn get_trigger_pc(&self) -> Option<u64> {
+        self.emu.get_data().trigger_pc
+    }
+
+    /// Get number of executed instructions of the current run
+    ///
+    /// Only counted with `count_instructions` in the configuration
+    pub fn get_instruction_count(&self) -> usize {
+        self.emu.get_data().instruction_count
+    }
+
+    /// Get lowest stack pointer of the current run
+    ///
+    /// Only recorded with `track_stack_usage` in the configuration
+    pub fn min_sp_reached(&self) -> Option<u64> {
+        self.emu.get_data().min_stack_pointer
+    }
+
+    /// Get maximum stack usage in bytes of the current run, measured from the
+    /// initial stack pointer
+    pub fn max_stack_usage(&self) -> Option<u64> {
+        let (stack_base, stack_size) = self.get_stack_region();
+        self.min_sp_reached()
+            .map(|sp| (stack_base + stack_size).saturating_sub(sp))
+    }
+
+    /// Get number of clock cycles of the current run
+    ///
+    /// Only counted with `count_cycles` in the configuration
+    pub fn cycles_executed(&self) -> usize {
+        self.emu.get_data().cycle_count
+    }
+
+    /// Get serial output (printf) of the current run
+    pub fn get_serial_output(&self) -> &[u8] {
+        &self.emu.get_data().serial_output
     }
 
     /// Get current state of simulation
@@ -291,16 +1240,154 @@ impl<'a> Cpu<'a> {
         &mut self.emu.get_data_mut().fault_data
     }
 
+    /// Get address ranges (begin, end inclusive) of the code for code hooks
+    fn code_ranges(&self) -> Vec<(u64, u64)> {
+        // Code is only contained in the file part of an executable segment,
+        // the remaining part up to p_memsz is zero initialized data
+        self.emu
+            .get_data()
+            .file_data
+            .program_data
+            .iter()
+            .filter(|segment| segment.0.p_flags & PF_X != 0 && segment.0.p_filesz != 0)
+            .map(|segment| {
+                (
+                    segment.0.p_paddr,
+                    segment.0.p_paddr + segment.0.p_filesz - 1,
+                )
+            })
+            .collect()
+    }
+
     /// Set code hook for tracing
     pub fn set_trace_hook(&mut self) {
-        // TODO: go through all program data parts
+        // Hook is kept for further runs
+        if self.trace_hook_active {
+            return;
+        }
+        for (begin, end) in self.code_ranges() {
+            let hook = self
+                .emu
+                .add_code_hook(begin, end, hook_code_callback)
+                .expect("failed to setup trace hook");
+            self.hooks.push(hook);
+        }
+        self.trace_hook_active = true;
+    }
+
+    /// Call `callback` with the address of each executed instruction
+    ///
+    /// Nothing is recorded internally, so together with `RunType::Run` this is a
+    /// streaming alternative to the buffered trace. The callback is owned by the
+    /// unicorn instance: it can only borrow data which outlives the elf file and
+    /// the configuration (`'a`) and has no access to the simulation itself. It stays
+    /// active for all following runs until the elf file is reloaded
+    pub fn set_trace_callback<F: FnMut(u64) + 'a>(
+        &mut self,
+        mut callback: F,
+    ) -> Result<(), uc_error> {
+        // A range with begin > end hooks all addresses, so one hook covers all code segments
+        let hook = self
+            .emu
+            .add_code_hook(1, 0, move |_: &mut Unicorn<CpuState>, address, _| {
+                callback(address)
+            })?;
+        self.hooks.push(hook);
+        Ok(())
+    }
+
+    /// Set memory read hook for a data fault
+    ///
+    /// The mask (in memory byte order) is XORed to the memory at the address on the
+    /// next read access, so writes of the program before the read are kept
+    pub fn set_data_fault_hook(&mut self, address: u64, xor_mask: Vec<u8>) -> Result<(), uc_error> {
+        let end = address + xor_mask.len() as u64 - 1;
         self.emu
-            .add_code_hook(
-                self.emu.get_data().file_data.program_data[0].0.p_paddr,
-                self.emu.get_data().file_data.program_data[0].0.p_memsz,
-                hook_code_callback,
-            )
-            .expect("failed to setup trace hook");
+            .get_data_mut()
+            .data_faults
+            .push((address, xor_mask));
+        let hook = self.emu.add_mem_hook(
+            HookType::MEM_READ,
+            address,
+            end,
+            hook_mem_read_data_fault_callback,
+        )?;
+        self.data_fault_hooks.push((address, hook));
+        Ok(())
+    }
+
+    /// Remove hooks of data faults which are already applied
+    ///
+    /// Hooks are not removed inside of their callback, so this is done after each
+    /// emulation step
+    fn release_applied_data_fault_hooks(&mut self) {
+        let pending = &self.emu.get_data().data_faults;
+        let (active, applied): (Vec<_>, Vec<_>) = self
+            .data_fault_hooks
+            .drain(..)
+            .partition(|(address, _)| pending.iter().any(|(pending, _)| pending == address));
+        self.data_fault_hooks = active;
+        for (_, hook) in applied {
+            if let Err(error) = self.emu.remove_hook(hook) {
+                warn!("Failed to remove data fault hook: {:?}", error);
+            }
+        }
+    }
+
+    /// Unmap pages which were mapped on invalid accesses of a previous run
+    fn unmap_pages_of_invalid_accesses(&mut self) {
+        for page in std::mem::take(&mut self.emu.get_data_mut().mapped_pages) {
+            if let Err(error) = self.emu.mem_unmap(page, MINIMUM_MEMORY_SIZE) {
+                warn!("Failed to unmap page 0x{:X}: {:?}", page, error);
+            }
+        }
+    }
+
+    /// Remove hooks of data faults of a previous run
+    fn release_data_fault_hooks(&mut self) {
+        for (_, hook) in self.data_fault_hooks.drain(..) {
+            if let Err(error) = self.emu.remove_hook(hook) {
+                warn!("Failed to remove data fault hook: {:?}", error);
+            }
+        }
+    }
+
+    /// Record all write accesses to the memory range `address..address + len`
+    ///
+    /// If `halt` is set, the emulation is stopped with `StopReason::Watchpoint`
+    /// at the first write which changes the content. The watchpoint stays
+    /// active until the elf file is reloaded, the log is cleared with each run
+    pub fn add_watchpoint(&mut self, address: u64, len: usize, halt: bool) -> Result<(), uc_error> {
+        if len == 0 {
+            return Err(uc_error::ARG);
+        }
+        let hook = self.emu.add_mem_hook(
+            HookType::MEM_WRITE,
+            address,
+            address + len as u64 - 1,
+            move |emu: &mut Unicorn<CpuState>, _, address, size, value| {
+                watchpoint_write_callback(emu, address, size, value, halt)
+            },
+        )?;
+        self.hooks.push(hook);
+        Ok(())
+    }
+
+    /// Get write accesses to the watched memory ranges of the current run
+    pub fn get_watch_log(&self) -> &[WatchRecord] {
+        &self.emu.get_data().watch_log
+    }
+
+    /// Get memory writes of the current run
+    ///
+    /// Only recorded with `write_log_limit` in the configuration
+    pub fn get_write_log(&self) -> &[WriteRecord] {
+        &self.emu.get_data().write_log
+    }
+
+    /// Get invalid memory accesses of the current run
+    pub fn get_invalid_accesses(&self) -> &[InvalidAccess] {
+        &self.emu.get_data().invalid_accesses
     }
 
     pub fn start_tracing(&mut self, with_register_data: bool) {
@@ -320,6 +1407,16 @@ impl<'a> Cpu<'a> {
         &mut self.emu.get_data_mut().trace_data
     }
 
+    /// Get fingerprint of the recorded trace and the state of the current run
+    ///
+    /// Runs with identical program flow and result have the same fingerprint.
+    /// The fingerprint depends on the order of the executed addresses, so it
+    /// requires a full trace which is not reduced
+    pub fn trace_fingerprint(&self) -> u64 {
+        let cpu_state = self.emu.get_data();
+        trace_fingerprint(&cpu_state.trace_data, cpu_state.state)
+    }
+
     /// Remove duplicates to speed up testing
     pub fn reduce_trace(&mut self) {
         let trace_data = &mut self.emu.get_data_mut().trace_data;
@@ -327,10 +1424,45 @@ impl<'a> Cpu<'a> {
         trace_data.retain(|trace| seen.insert(trace.clone()));
     }
 
+    /// Check if the address is part of the loaded code
+    pub fn is_code_address(&self, address: u64) -> bool {
+        self.emu
+            .get_data()
+            .file_data
+            .program_data
+            .iter()
+            .filter(|segment| segment.0.p_flags & PF_X != 0)
+            .any(|segment| {
+                address >= segment.0.p_paddr && address < segment.0.p_paddr + segment.0.p_filesz
+            })
+    }
+
     /// Execute fault injection according to fault type
     /// Program is stopped and will be continued after fault injection
-    pub fn execute_fault_injection(&mut self, fault: &FaultRecord) -> bool {
-        fault.fault_type.execute(self, fault)
+    ///
+    /// The program counter has to be located in the loaded code, otherwise an error is returned
+    pub fn execute_fault_injection(&mut self, fault: &FaultRecord) -> Result<bool, String> {
+        let address = self.program_counter;
+        if !self.is_code_address(address) {
+            return Err(format!(
+                "Fault address 0x{:X} out of range of the loaded code ({:?})",
+                address, fault.fault_type
+            ));
+        }
+        let count = self.emu.get_data().fault_data.len();
+        let (thumb, target_arch) = (self.is_thumb_state(), self.get_target_arch());
+        let result = fault.fault_type.execute(self, fault)?;
+        for data in &mut self.emu.get_data_mut().fault_data[count..] {
+            data.thumb = thumb;
+            data.target_arch = target_arch;
+            if data.changes_first_half_only(address) {
+                warn!(
+                    "{:?} changes only the first half of the 32 bit instruction at 0x{:X}",
+                    fault.fault_type, address
+                );
+            }
+        }
+        Ok(result)
     }
 
     /// Get Program counter from internal variable
@@ -349,12 +1481,55 @@ impl<'a> Cpu<'a> {
         self.emu.reg_read(regid)
     }
 
+    /// Read register value of an AArch64 target
+    ///
+    pub fn register_read_arm64(&self, regid: RegisterARM64) -> Result<u64, uc_error> {
+        self.emu.reg_read(regid)
+    }
+
     /// Write register value
     ///
     pub fn register_write(&mut self, regid: RegisterARM, value: u64) -> Result<(), uc_error> {
         self.emu.reg_write(regid, value)
     }
 
+    /// Read the flags register (xPSR on Cortex-M, CPSR on ARM and NZCV on AArch64)
+    ///
+    /// The condition flags N, Z, C and V are located in bits 31 to 28 on all targets
+    pub fn read_flags(&self) -> Result<u64, uc_error> {
+        match self.get_target_arch() {
+            TargetArch::CortexM => self.emu.reg_read(RegisterARM::XPSR),
+            TargetArch::Arm => self.emu.reg_read(RegisterARM::CPSR),
+            TargetArch::AArch64 => self.emu.reg_read(RegisterARM64::NZCV),
+        }
+    }
+
+    /// Write the flags register (see `read_flags`)
+    ///
+    /// The execution state bits are written as well, so they should be taken from
+    /// `read_flags` when only the condition flags are changed
+    pub fn write_flags(&mut self, value: u64) -> Result<(), uc_error> {
+        match self.get_target_arch() {
+            TargetArch::CortexM => self.emu.reg_write(RegisterARM::XPSR, value),
+            TargetArch::Arm => self.emu.reg_write(RegisterARM::CPSR, value),
+            TargetArch::AArch64 => self.emu.reg_write(RegisterARM64::NZCV, value),
+        }
+    }
+
+    /// Read all registers of `ARM_REG`
+    ///
+    /// Returns None for AArch64 targets
+    pub fn registers_read(&self) -> Option<[u64; 17]> {
+        if !self.get_target_arch().is_aarch32() {
+            return None;
+        }
+        let mut registers: [u64; 17] = [0; 17];
+        ARM_REG.iter().enumerate().for_each(|(index, register)| {
+            registers[index] = self.emu.reg_read(*register).unwrap();
+        });
+        Some(registers)
+    }
+
     /// Read memory
     ///
     pub fn memory_read(&self, address: u64, buffer: &mut [u8]) -> Result<(), uc_error> {
@@ -364,6 +1539,7 @@ impl<'a> Cpu<'a> {
     /// Write memory
     ///
     pub fn memory_write(&mut self, address: u64, buffer: &[u8]) -> Result<(), uc_error> {
+        self.code_loaded = false;
         self.emu.mem_write(address, buffer)
     }
 
@@ -383,9 +1559,270 @@ impl<'a> Cpu<'a> {
     ///
     pub fn asm_cmd_write(&mut self, address: u64, instruction: &[u8]) -> Result<(), uc_error> {
         // Write assembler instruction to memory
-        self.memory_write(address, instruction).unwrap();
+        self.memory_write(address, instruction)?;
         // Clear cached instruction
         self.emu
             .ctl_remove_cache(address, address + instruction.len() as u64)
     }
 }
+
+/// Release hooks on teardown without panicking, errors are only logged
+impl Drop for Cpu<'_> {
+    fn drop(&mut self) {
+        if let Err(error) = self.remove_hooks() {
+            warn!("Failed to remove hooks: {:?}", error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thumb_cmd_size_endianness() {
+        // "push {r7, lr}" (0xb580), "bl" (0xf7ff 0xfcec), "ldr.w" (0xf851 0x2b1c)
+        let stream = [0x80, 0xb5, 0xff, 0xf7, 0xec, 0xfc, 0x51, 0xf8, 0x1c, 0x2b];
+        let mut sizes = Vec::new();
+        let mut offset = 0;
+        while offset < stream.len() {
+            let size = thumb_cmd_size([stream[offset], stream[offset + 1]]);
+            sizes.push(size);
+            offset += size;
+        }
+        assert_eq!(sizes, [2, 4, 4]);
+
+        // Code of a BE8 target is little endian, only data is big endian
+        let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+        let main = file_data.symbol_map["main"].st_value & !1;
+        let little = SimulationConfig::default();
+        let big = SimulationConfig {
+            endianness: Endianness::Big,
+            ..Default::default()
+        };
+        let sizes: Vec<Vec<usize>> = [&little, &big]
+            .iter()
+            .map(|config| {
+                let mut cpu = Cpu::new(&file_data, config).unwrap();
+                cpu.setup_mmio().unwrap();
+                cpu.load_code();
+                cpu.deactivate_printf_function();
+                let serial_puts = file_data.symbol_map["serial_puts"].st_value & !1;
+                let mut ret_cmd = [0; 2];
+                cpu.memory_read(serial_puts, &mut ret_cmd).unwrap();
+                assert_eq!(ret_cmd, T1_RET);
+                (0..16)
+                    .map(|offset| cpu.get_asm_cmd_size(main + 2 * offset).unwrap())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(sizes[0], sizes[1]);
+    }
+
+    #[test]
+    fn thumb_bit_masking() {
+        assert_eq!(thumb_addr(0x08000628), 0x08000629);
+        assert_eq!(thumb_addr(0x08000629), 0x08000629);
+        assert_eq!(clear_thumb(0x08000629), 0x08000628);
+        assert_eq!(clear_thumb(0x08000628), 0x08000628);
+        // Upper bits of 64 bit addresses are kept
+        assert_eq!(clear_thumb(0x1_0000_0001), 0x1_0000_0000);
+    }
+
+    #[test]
+    fn end_address_alignment() {
+        // End of victim_.elf code segment (0x08000000 + 0x6c5)
+        assert_eq!(align_end_address(0x080006c5, true), 0x080006c4);
+        assert_eq!(align_end_address(0x080006c5, false), 0x080006c4);
+        assert_eq!(align_end_address(0x080006c6, true), 0x080006c6);
+        assert_eq!(align_end_address(0x080006c6, false), 0x080006c4);
+    }
+
+    #[test]
+    fn odd_code_end_terminates() {
+        let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+        let code = &file_data.program_data[0].0;
+        let end = code.p_paddr + code.p_filesz;
+        assert_eq!(end & 1, 1);
+        let config = SimulationConfig::default();
+        let mut cpu = Cpu::new(&file_data, &config).unwrap();
+        cpu.setup_mmio().unwrap();
+        cpu.init_register();
+        cpu.load_code();
+        cpu.init_states(false);
+
+        // Two "nop" in front of the aligned end address
+        let end = end & !1;
+        cpu.memory_write(end - 4, &[0x00, 0xbf, 0x00, 0xbf])
+            .unwrap();
+        cpu.set_program_counter(end - 4);
+        assert_eq!(cpu.run_steps(10, false), StopReason::EndAddress);
+        assert_eq!(cpu.emu.pc_read().unwrap(), end);
+    }
+
+    #[test]
+    fn stack_region_without_section() {
+        let mut file_data =
+            ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+        let stack = file_data.section_map.remove(".stack").unwrap();
+        let config = SimulationConfig::default();
+        assert_eq!(Cpu::new(&file_data, &config).err(), Some(uc_error::ARG));
+
+        let layout = MemoryLayout {
+            stack_base: Some(stack.sh_addr),
+            stack_size: Some(stack.sh_size),
+            ..Default::default()
+        };
+        assert_eq!(
+            stack_region(&file_data, layout),
+            Ok((stack.sh_addr, stack.sh_size))
+        );
+    }
+
+    #[test]
+    fn stack_outside_segments_cleared() {
+        let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+        let config = SimulationConfig {
+            memory_layout: MemoryLayout {
+                stack_base: Some(0x2001_0000),
+                stack_size: Some(0x1000),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut cpu = Cpu::new(&file_data, &config).unwrap();
+        cpu.setup_mmio().unwrap();
+        assert!(!cpu.stack_in_segments());
+        cpu.load_code();
+        cpu.memory_write(0x2001_0ffc, &[0xaa; 4]).unwrap();
+
+        // Stack content of the previous run is removed
+        cpu.load_code();
+        let mut data = [0xff; 4];
+        cpu.memory_read(0x2001_0ffc, &mut data).unwrap();
+        assert_eq!(data, [0; 4]);
+    }
+
+    #[test]
+    fn trace_fingerprint_order() {
+        let record = |address| TraceRecord::Instruction {
+            address,
+            index: 0,
+            asm_instruction: vec![0x00, 0xbf],
+            thumb: true,
+            registers: None,
+        };
+        let trace = [record(0x8000000), record(0x8000002)];
+        let reversed = [record(0x8000002), record(0x8000000)];
+
+        let fingerprint = trace_fingerprint(&trace, RunState::Failed);
+        assert_eq!(fingerprint, trace_fingerprint(&trace, RunState::Failed));
+        assert_ne!(fingerprint, trace_fingerprint(&reversed, RunState::Failed));
+        assert_ne!(fingerprint, trace_fingerprint(&trace, RunState::Success));
+    }
+
+    #[test]
+    fn nop_cmd_per_arch() {
+        let thumb = nop_cmd(TargetArch::CortexM, true, 4).unwrap();
+        assert_eq!(thumb, [0x00, 0xbf, 0x00, 0xbf]);
+        let arm = nop_cmd(TargetArch::Arm, false, 4).unwrap();
+        assert_eq!(arm, A32_NOP);
+        let aarch64 = nop_cmd(TargetArch::AArch64, false, 4).unwrap();
+        assert_eq!(aarch64, A64_NOP);
+        // 4 byte nop does not fit into a 2 byte slot
+        assert!(nop_cmd(TargetArch::Arm, false, 2).is_err());
+    }
+
+    #[test]
+    fn nop_cmd_big_endian() {
+        let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+        for target_arch in [TargetArch::CortexM, TargetArch::AArch64] {
+            let config = SimulationConfig {
+                target_arch,
+                endianness: Endianness::Big,
+                ..Default::default()
+            };
+            let cpu = Cpu::new(&file_data, &config).unwrap();
+            let nop = cpu.nop_cmd(4).unwrap();
+            let cs = crate::disassembly::Disassembly::with_target(target_arch, Endianness::Big);
+            let disassembly = cs.instructions_to_string(&nop, 0x1000);
+            assert!(
+                disassembly
+                    .split("; ")
+                    .all(|instruction| instruction == "nop"),
+                "{:?}: {}",
+                target_arch,
+                disassembly
+            );
+        }
+    }
+
+    #[test]
+    fn data_fault_hooks_released() {
+        let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+        let config = SimulationConfig::default();
+        let mut cpu = Cpu::new(&file_data, &config).unwrap();
+        cpu.setup_mmio().unwrap();
+        cpu.load_code();
+
+        // Arm two data faults without releasing them
+        cpu.set_data_fault_hook(0x20000000, vec![0x01]).unwrap();
+        cpu.set_data_fault_hook(0x20000004, vec![0x02]).unwrap();
+        assert_eq!(cpu.data_fault_hooks.len(), 2);
+
+        // Next run starts without the faults of the previous one
+        cpu.init_states(false);
+        assert!(cpu.data_fault_hooks.is_empty());
+        assert!(cpu.emu.get_data().data_faults.is_empty());
+    }
+
+    #[test]
+    fn data_fault_on_current_value() {
+        let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+        let config = SimulationConfig::default();
+        let mut cpu = Cpu::new(&file_data, &config).unwrap();
+        cpu.setup_mmio().unwrap();
+        cpu.init_register();
+        cpu.load_code();
+        cpu.init_states(false);
+
+        // "movs r0, #0x20", "lsls r0, r0, #24", "movs r1, #0x5a", "str r1, [r0]",
+        // "ldr r4, [r0, #4]", "ldr r2, [r0]", "ldr r3, [r0]"
+        let entry = file_data.entry_point & !1;
+        let code = [
+            0x20, 0x20, 0x00, 0x06, 0x5a, 0x21, 0x01, 0x60, 0x44, 0x68, 0x02, 0x68, 0x03, 0x68,
+        ];
+        cpu.memory_write(entry, &code).unwrap();
+        cpu.set_program_counter(entry);
+        cpu.set_data_fault_hook(0x2000_0000, vec![0x01, 0x00, 0x00, 0x00])
+            .unwrap();
+        cpu.run_steps(7, false);
+
+        // Value written after injection is flipped once, the neighbouring read is not faulted
+        assert_eq!(cpu.register_read(RegisterARM::R2).unwrap(), 0x5b);
+        assert_eq!(cpu.register_read(RegisterARM::R3).unwrap(), 0x5b);
+        assert!(cpu.emu.get_data().data_faults.is_empty());
+        assert!(cpu.data_fault_hooks.is_empty());
+    }
+
+    #[test]
+    fn fault_out_of_range() {
+        let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+        let config = SimulationConfig::default();
+        let mut cpu = Cpu::new(&file_data, &config).unwrap();
+        cpu.setup_mmio().unwrap();
+        cpu.load_code();
+
+        let fault = FaultRecord {
+            index: 0,
+            fault_type: crate::fault_attacks::faults::Glitch::new(1),
+        };
+        // Data memory is not part of the loaded code
+        cpu.set_program_counter(0x20000000);
+        let error = cpu.execute_fault_injection(&fault).unwrap_err();
+        assert!(error.contains("out of range"), "{}", error);
+        // Code of main
+        cpu.set_program_counter(0x8000630);
+        assert!(cpu.execute_fault_injection(&fault).is_ok());
+    }
+}
@@ -0,0 +1,60 @@
+use crate::elf_file::{ElfFile, EM_AARCH64};
+use elf::endian::AnyEndian;
+use unicorn_engine::unicorn_const::{Arch, Mode};
+
+pub use crate::data::TargetArch;
+
+impl TargetArch {
+    /// Detect the target architecture from the elf file header
+    pub fn from_elf(file_data: &ElfFile) -> Self {
+        match file_data.header.e_machine {
+            EM_AARCH64 => TargetArch::AArch64,
+            _ => TargetArch::CortexM,
+        }
+    }
+
+    /// Get unicorn architecture and mode for the target with the given byte order
+    ///
+    /// Big endian 32 bit targets use BE8 (big endian data, little endian code),
+    /// AArch64 fetches instructions little endian in any case
+    pub fn unicorn_arch_mode(&self, endianness: Endianness) -> (Arch, Mode) {
+        let (endian_mode, aarch32_mode) = match endianness {
+            Endianness::Little => (Mode::LITTLE_ENDIAN, Mode::LITTLE_ENDIAN),
+            Endianness::Big => (Mode::BIG_ENDIAN, Mode::BIG_ENDIAN | Mode::ARMBE8),
+        };
+        match self {
+            TargetArch::CortexM => (Arch::ARM, aarch32_mode | Mode::MCLASS),
+            TargetArch::AArch64 => (Arch::ARM64, endian_mode),
+            TargetArch::Arm => (Arch::ARM, aarch32_mode | Mode::ARM),
+        }
+    }
+
+    /// Check if the target executes Thumb code only
+    pub fn is_thumb(&self) -> bool {
+        *self == TargetArch::CortexM
+    }
+
+    /// Check if the target uses the 32 bit register set of `ARM_REG`
+    pub fn is_aarch32(&self) -> bool {
+        matches!(self, TargetArch::CortexM | TargetArch::Arm)
+    }
+}
+
+/// Byte order of the target
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Endianness {
+    #[default]
+    Little,
+    /// Big endian (BE8) target
+    Big,
+}
+
+impl Endianness {
+    /// Detect the byte order from the elf file header
+    pub fn from_elf(file_data: &ElfFile) -> Self {
+        match file_data.header.endianness {
+            AnyEndian::Big => Endianness::Big,
+            AnyEndian::Little => Endianness::Little,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Simple timing model of the executed instructions
+use super::TargetArch;
+
+/// Read little endian halfword of an instruction
+///
+/// Instructions are little endian on all supported targets (BE8)
+fn halfword(data: &[u8]) -> u16 {
+    u16::from_le_bytes([data[0], data[1]])
+}
+
+/// Approximate number of clock cycles of an instruction
+///
+/// The cycles are taken from the instruction class of a Cortex-M core without
+/// wait states. Branches are counted as taken and divisions with their worst
+/// case. Instructions of ARM and AArch64 targets count one cycle
+pub(crate) fn instruction_cycles(instruction: &[u8], target_arch: TargetArch) -> usize {
+    if target_arch != TargetArch::CortexM || instruction.len() < 2 {
+        return 1;
+    }
+    let first = halfword(instruction);
+    if instruction.len() == 2 {
+        return match first {
+            // PUSH / POP
+            _ if first & 0xf600 == 0xb400 => 1 + (first & 0x1ff).count_ones() as usize,
+            // LDM / STM
+            _ if first & 0xf000 == 0xc000 => 1 + (first & 0xff).count_ones() as usize,
+            // Load / store
+            _ if first & 0xf800 == 0x4800 || (0x5000..0xa000).contains(&first) => 2,
+            // B<cond> (without UDF and SVC), B, BX and BLX
+            _ if first & 0xf000 == 0xd000 && first & 0x0e00 != 0x0e00 => 2,
+            _ if first & 0xf800 == 0xe000 || first & 0xff00 == 0x4700 => 2,
+            _ => 1,
+        };
+    }
+    if instruction.len() < 4 {
+        return 1;
+    }
+    let second = halfword(&instruction[2..]);
+    match first {
+        // BL
+        _ if first & 0xf800 == 0xf000 && second & 0xd000 == 0xd000 => 3,
+        // B.W and B<cond>.W
+        _ if first & 0xf800 == 0xf000 && second & 0x8000 == 0x8000 => 2,
+        // LDM / STM and LDRD / STRD
+        _ if first & 0xfe40 == 0xe800 => 1 + second.count_ones() as usize,
+        _ if first & 0xfe40 == 0xe840 => 3,
+        // Load / store
+        _ if first & 0xfe00 == 0xf800 => 2,
+        // SDIV / UDIV
+        _ if first & 0xffd0 == 0xfb90 => 12,
+        // Multiply (accumulate) and long multiply
+        _ if first & 0xff80 == 0xfb00 => 2,
+        _ if first & 0xff80 == 0xfb80 => 3,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles_of_instruction_classes() {
+        let cycles = |instruction: &[u8]| instruction_cycles(instruction, TargetArch::CortexM);
+        // "nop", "adds r0, r1, #4"
+        assert_eq!(cycles(&[0x00, 0xbf]), 1);
+        assert_eq!(cycles(&[0x08, 0x1d]), 1);
+        // "ldr r1, [pc, #44]", "ldr.w r2, [r1], #28"
+        assert_eq!(cycles(&[0x0b, 0x49]), 2);
+        assert_eq!(cycles(&[0x51, 0xf8, 0x1c, 0x2b]), 2);
+        // "push {r7, lr}", "beq", "bl"
+        assert_eq!(cycles(&[0x80, 0xb5]), 3);
+        assert_eq!(cycles(&[0x02, 0xd0]), 2);
+        assert_eq!(cycles(&[0x00, 0xf0, 0x02, 0xf8]), 3);
+        // "sdiv r0, r0, r1"
+        assert_eq!(cycles(&[0x90, 0xfb, 0xf1, 0xf0]), 12);
+        // Instructions of other targets
+        assert_eq!(
+            instruction_cycles(&[0x1e, 0xff, 0x2f, 0xe1], TargetArch::Arm),
+            1
+        );
+    }
+}
@@ -1,24 +1,153 @@
-use crate::simulation::record::{FaultRecord, TraceRecord};
-
-#[derive(Clone, Debug)]
-/// Representation of an fault which was executed in a simulation.
-pub struct FaultData {
-    /// The original instructions which would have been performed without the fault.
-    pub original_instruction: Vec<u8>,
-    /// The modified instructions if it was changed which is performed with the fault.
-    pub modified_instruction: Vec<u8>,
-    /// The recorded execution trace of this fault.
-    pub record: TraceRecord,
-    /// FaultRecord which caused this FaultData to be simulated.
-    pub fault: FaultRecord,
-}
+use crate::data;
+use crate::disassembly::Disassembly;
+use crate::fault_attacks::faults::FaultType;
+use std::collections::{BTreeSet, HashSet};
+
+/// Effective fault: (address, fault type, description, changed bytes (offset, value))
+type FaultKey = (u64, &'static str, String, Vec<(usize, u8)>);
+
+/// Fault data of a simulation with the executable fault type
+pub type FaultData = data::FaultData<FaultType>;
 
 impl FaultData {
-    /// Generate out of a FaultData array a FaultRecord array
-    pub fn get_simulation_fault_records(fault_data_records: &[FaultData]) -> Vec<FaultRecord> {
-        fault_data_records
-            .iter()
-            .map(|record| record.fault.clone())
+    /// Get a data-only copy of the fault data with the name of the fault type
+    pub fn descriptor(&self) -> data::FaultData<String> {
+        data::FaultData {
+            original_instruction: self.original_instruction.clone(),
+            modified_instruction: self.modified_instruction.clone(),
+            instruction_size: self.instruction_size,
+            original_register_value: self.original_register_value,
+            final_registers: self.final_registers,
+            trigger: self.trigger.clone(),
+            run_duration: self.run_duration,
+            thumb: self.thumb,
+            target_arch: self.target_arch,
+            record: self.record.clone(),
+            fault: self.fault.descriptor(),
+        }
+    }
+
+    /// Remove attacks whose set of effective faults is already part of the results
+    ///
+    /// Faults are compared by address, fault type with its parameters (description)
+    /// and changed bytes, so the same fault nested in different combinations is
+    /// reported once. The first attack of each set is kept
+    pub fn dedupe_results(results: Vec<Vec<FaultData>>) -> Vec<Vec<FaultData>> {
+        let mut known: HashSet<BTreeSet<FaultKey>> = HashSet::new();
+        results
+            .into_iter()
+            .filter(|attack| known.insert(attack.iter().map(FaultData::key).collect()))
             .collect()
     }
+
+    /// Get key of the effective fault for deduplication
+    fn key(&self) -> FaultKey {
+        let changed = self
+            .modified_instruction
+            .iter()
+            .enumerate()
+            .filter(|(offset, value)| self.original_instruction.get(*offset) != Some(value))
+            .map(|(offset, value)| (offset, *value))
+            .collect();
+        (
+            self.record.address(),
+            self.fault.fault_type.name(),
+            self.fault.fault_type.description(),
+            changed,
+        )
+    }
+
+    /// Disassemble the original and the faulted instructions at the fault address
+    ///
+    /// Returns (original, faulted) mnemonics. The faulted string is empty if the
+    /// fault did not modify the code (e.g. register faults). The instructions are
+    /// decoded in the state of the target when the fault was injected
+    pub fn disassemble(&self) -> (String, String) {
+        let cs = Disassembly::with_state(self.target_arch, self.thumb);
+        let address = self.record.address();
+        (
+            cs.instructions_to_string(&self.original_instruction, address),
+            cs.instructions_to_string(&self.modified_instruction, address),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fault_attacks::faults::{CmdBitFlip, Glitch};
+    use crate::simulation::record::{FaultRecord, TraceRecord};
+
+    fn fault_data(address: u64, modified_instruction: Vec<u8>) -> FaultData {
+        let record = TraceRecord::Fault {
+            address,
+            fault_type: "test".to_string(),
+            data: vec![0; 4],
+        };
+        let fault = FaultRecord {
+            index: 0,
+            fault_type: Glitch::new(1),
+        };
+        FaultData::new(record, fault, vec![0; 4], modified_instruction)
+    }
+
+    #[test]
+    fn diff_of_injected_bytes() {
+        let first = fault_data(0x8000000, vec![0x00, 0xbf, 0x00, 0xbf]);
+        let second = fault_data(0x8000000, vec![0x00, 0xbe]);
+        assert_eq!(first.diff(&second), vec![(1, 0xbf, 0xbe), (3, 0xbf, 0x00)]);
+        assert!(first.diff(&first).is_empty());
+        assert_eq!(first, first.clone());
+        assert_ne!(first, second);
+        assert_ne!(first, fault_data(0x8000002, vec![0x00, 0xbf, 0x00, 0xbf]));
+    }
+
+    #[test]
+    fn display_fault() {
+        let glitch: FaultType = Glitch::new(3);
+        let bitflip: FaultType = CmdBitFlip::new(0x20);
+        assert_eq!(glitch.to_string(), "NOP x3");
+        assert_eq!(bitflip.to_string(), "bitflip@bit5");
+        assert_eq!(
+            fault_data(0x8000000, vec![0x00, 0xbf, 0x00, 0xbf]).to_string(),
+            "0x08000000 NOP x1: 00000000 -> 00bf00bf"
+        );
+    }
+
+    #[test]
+    fn data_only_descriptor() {
+        let fault_data = fault_data(0x8000000, vec![0x00, 0xbf, 0x00, 0xbf]);
+        let descriptor = fault_data.descriptor();
+        assert_eq!(descriptor.fault.fault_type, "NOP x1");
+        assert_eq!(descriptor.to_string(), fault_data.to_string());
+        assert!(descriptor.diff(&fault_data.descriptor()).is_empty());
+    }
+
+    #[test]
+    fn dedupe_nested_faults() {
+        let first = fault_data(0x8000000, vec![0x00, 0xbf, 0x00, 0x00]);
+        let second = fault_data(0x8000010, vec![0x00, 0xbf, 0x00, 0x00]);
+        let results = FaultData::dedupe_results(vec![
+            vec![first.clone()],
+            vec![first.clone(), second.clone()],
+            vec![second, first.clone()],
+            vec![first.clone(), first],
+        ]);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].len(), 1);
+        assert_eq!(results[1].len(), 2);
+    }
+
+    #[test]
+    fn first_half_of_instruction_changed() {
+        assert!(
+            fault_data(0x8000000, vec![0x01, 0x00, 0x00, 0x00]).changes_first_half_only(0x8000000)
+        );
+        assert!(
+            !fault_data(0x8000000, vec![0x00, 0x00, 0x01, 0x00]).changes_first_half_only(0x8000000)
+        );
+        assert!(!fault_data(0x8000000, vec![0x00; 4]).changes_first_half_only(0x8000000));
+        // Region of a byte set fault starting at the second half
+        assert!(!fault_data(0x8000002, vec![0x01, 0x00]).changes_first_half_only(0x8000000));
+    }
 }
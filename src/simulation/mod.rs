@@ -1,13 +1,24 @@
+pub mod config;
 pub mod cpu;
 pub mod fault_data;
 pub mod record;
+#[cfg(feature = "serde")]
+pub mod serialize;
 
 use crate::elf_file::ElfFile;
-use cpu::{Cpu, RunState};
+use config::SimulationConfig;
+use cpu::{Cpu, InvalidAccess, RunState, Snapshot, StopReason, TargetArch, ARM64_REG, ARM_REG};
 use fault_data::FaultData;
-use log::info;
+use log::{debug, error, info};
 use record::FaultRecord;
-pub use record::TraceRecord;
+pub use record::{TraceRecord, WatchRecord, WriteRecord};
+use std::collections::HashSet;
+use std::fmt;
+use std::time::{Duration, Instant};
+use unicorn_engine::unicorn_const::{uc_error, Permission};
+
+/// Default maximum number of executed instructions per run
+pub const DEFAULT_INSTRUCTION_LIMIT: usize = 2000;
 
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum RunType {
@@ -22,32 +33,311 @@ pub enum Data {
     None,
 }
 
+/// Failed path of the program check with the observed state
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ProgramCheckError {
+    /// Success run did not end in `RunState::Success`
+    SuccessPath(RunState),
+    /// Failure run did not end in `RunState::Failed`
+    FailurePath(RunState),
+}
+
+impl fmt::Display for ProgramCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (path, state) = match self {
+            ProgramCheckError::SuccessPath(state) => ("Success", state),
+            ProgramCheckError::FailurePath(state) => ("Failure", state),
+        };
+        write!(
+            f,
+            "Program function check failed. {} path is not working properly! (state: {:?})",
+            path, state
+        )
+    }
+}
+
 pub struct Control<'a> {
     emu: Cpu<'a>,
+    instruction_limit: usize,
+    /// Upper bound of the instruction limit from the configuration
+    max_instruction_limit: Option<usize>,
+    run_duration: Duration,
 }
 
 impl<'a> Control<'a> {
     /// Create a new instance of the control module
     /// The elf file is used to load the program code
-    /// and to setup the cpu emulation
-    pub fn new(program_data: &'a ElfFile) -> Self {
+    /// and to setup the cpu emulation with the given configuration
+    ///
+    /// Errors of the memory mapping or hook setup are returned to the caller
+    pub fn new(program_data: &'a ElfFile, config: &'a SimulationConfig) -> Result<Self, uc_error> {
         // Setup cpu emulation
-        let mut emu = Cpu::new(program_data);
+        let mut emu = Cpu::new(program_data, config)?;
         // Cpu setup
-        emu.setup_mmio();
-        emu.setup_breakpoints();
-        Self { emu }
+        emu.setup_mmio()?;
+        emu.setup_breakpoints()?;
+        Ok(Self {
+            emu,
+            instruction_limit: config
+                .instruction_limit
+                .unwrap_or(DEFAULT_INSTRUCTION_LIMIT),
+            max_instruction_limit: config.instruction_limit,
+            run_duration: Duration::ZERO,
+        })
+    }
+
+    /// Reuse the simulation instance for another elf file
+    ///
+    /// This avoids the setup of a new unicorn instance when many files are processed
+    pub fn reload(&mut self, program_data: &'a ElfFile) -> Result<(), uc_error> {
+        self.emu.reload(program_data)
+    }
+
+    /// Get fault data of the last run
+    ///
+    /// Unlike the result of `run_with_faults` the data is also kept for runs which
+    /// were not successful. The final registers and the trigger are not set
+    pub fn get_fault_data(&mut self) -> Vec<FaultData> {
+        self.emu.get_fault_data().clone()
+    }
+
+    /// Get reason why the last execution stopped
+    pub fn get_stop_reason(&self) -> Option<StopReason> {
+        self.emu.get_stop_reason()
+    }
+
+    /// Set the maximum number of instructions executed per run
+    ///
+    /// Longer code under investigation requires a higher limit. Setting the
+    /// limit too high slows down fault campaigns as every run executes up to it.
+    /// The instruction limit of the configuration is not exceeded
+    pub fn set_instruction_limit(&mut self, instruction_limit: usize) {
+        self.instruction_limit = self
+            .max_instruction_limit
+            .map_or(instruction_limit, |limit| instruction_limit.min(limit));
+    }
+
+    /// Get serial output (printf) of the last run
+    ///
+    /// Output is only collected if the print deactivation is switched off in the configuration
+    pub fn get_serial_output(&self) -> &[u8] {
+        self.emu.get_serial_output()
+    }
+
+    /// Read memory of the emulation, e.g. to inspect a buffer after a run
+    ///
+    /// The memory is valid until the next run is started
+    pub fn read_memory(&self, address: u64, len: usize) -> Result<Vec<u8>, uc_error> {
+        let mut buffer = vec![0; len];
+        self.emu.memory_read(address, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Get the unicorn instance of the simulation (see `Cpu::unicorn_mut`)
+    ///
+    /// Bypassing the bookkeeping of the simulation is the responsibility of the caller
+    #[cfg(feature = "advanced")]
+    pub fn unicorn_mut(&mut self) -> &mut unicorn_engine::Unicorn<'a, cpu::CpuState<'a>> {
+        self.emu.unicorn_mut()
+    }
+
+    /// Get all mapped memory regions (start, size, permission)
+    ///
+    /// The list is read from the emulator, so it contains the program, stack, trigger
+    /// and MMIO regions of the current setup. Watchpoints are hooks and not listed
+    pub fn memory_regions(&self) -> Result<Vec<(u64, usize, Permission)>, uc_error> {
+        self.emu.memory_regions()
+    }
+
+    /// Write memory of the emulation, e.g. to place a key in RAM before a custom run
+    ///
+    /// Has to be called after `start` as the program is loaded there. Writes to
+    /// unmapped memory return an error
+    pub fn write_memory(&mut self, address: u64, data: &[u8]) -> Result<(), uc_error> {
+        self.emu.memory_write(address, data)
+    }
+
+    /// Read register of the emulation by its index
+    ///
+    /// The index refers to `ARM_REG` on 32 bit ARM and `ARM64_REG` on AArch64 targets
+    pub fn read_register(&self, reg_index: usize) -> Result<u64, uc_error> {
+        match self.emu.get_target_arch() {
+            TargetArch::CortexM | TargetArch::Arm => self
+                .emu
+                .register_read(*ARM_REG.get(reg_index).ok_or(uc_error::ARG)?),
+            TargetArch::AArch64 => self
+                .emu
+                .register_read_arm64(*ARM64_REG.get(reg_index).ok_or(uc_error::ARG)?),
+        }
+    }
+
+    /// Read the flags register of the emulation (see `Cpu::read_flags`)
+    pub fn read_flags(&self) -> Result<u64, uc_error> {
+        self.emu.read_flags()
+    }
+
+    /// Write the flags register of the emulation (see `Cpu::write_flags`)
+    ///
+    /// Has to be called after `start`, e.g. to set up a comparison result before `run_steps`
+    pub fn write_flags(&mut self, value: u64) -> Result<(), uc_error> {
+        self.emu.write_flags(value)
+    }
+
+    /// Get wall-clock time spent in the emulation of the last run with faults
+    ///
+    /// Only the execution itself is measured, not the setup of hooks and memory
+    pub fn get_run_duration(&self) -> Duration {
+        self.run_duration
+    }
+
+    /// Initialize and load the program for a custom run in failure mode
+    ///
+    /// Together with `run_steps`, `inject_fault`, `snapshot` and `restore` this allows
+    /// fault experiments which are not covered by `run_with_faults`
+    pub fn start(&mut self) {
+        self.init_and_load(false);
+        // Deactivate io print
+        if self.emu.get_config().deactivate_print {
+            self.emu.deactivate_printf_function();
+        }
+    }
+
+    /// Record all write accesses to the memory range `address..address + len`
+    ///
+    /// With `halt` set, `run_steps` stops with `StopReason::Watchpoint` at the
+    /// first write which changes the content
+    pub fn add_watchpoint(&mut self, address: u64, len: usize, halt: bool) -> Result<(), uc_error> {
+        self.emu.add_watchpoint(address, len, halt)
+    }
+
+    /// Get write accesses to the watched memory ranges of the last run
+    pub fn get_watch_log(&self) -> &[WatchRecord] {
+        self.emu.get_watch_log()
+    }
+
+    /// Get memory writes of the last run (see `SimulationConfig::write_log_limit`)
+    pub fn get_write_log(&self) -> &[WriteRecord] {
+        self.emu.get_write_log()
+    }
+
+    /// Get invalid memory accesses of the last run
+    pub fn get_invalid_accesses(&self) -> &[InvalidAccess] {
+        self.emu.get_invalid_accesses()
+    }
+
+    /// Call `callback` with the address of each executed instruction
+    ///
+    /// See `Cpu::set_trace_callback` for the lifetime constraints of the callback
+    pub fn set_trace_callback<F: FnMut(u64) + 'a>(&mut self, callback: F) -> Result<(), uc_error> {
+        self.emu.set_trace_callback(callback)
+    }
+
+    /// Get address of the instruction `count` instructions behind the given address
+    ///
+    /// Has to be called after `start` as the sizes are read from the loaded code
+    pub fn instruction_address(&self, address: u64, count: usize) -> Result<u64, uc_error> {
+        self.emu.instruction_address(address, count)
+    }
+
+    /// Inject a fault at the current program counter
+    ///
+    /// Returns true if the fault modified code which has to be restored by the caller
+    pub fn inject_fault(&mut self, fault: &FaultRecord) -> Result<bool, String> {
+        self.emu.execute_fault_injection(fault)
+    }
+
+    /// Get state of the current run
+    pub fn get_state(&self) -> RunState {
+        self.emu.get_state()
+    }
+
+    /// Get result of the first write to the auth address of the current run
+    pub fn get_auth_result(&self) -> Option<RunState> {
+        self.emu.get_auth_result()
+    }
+
+    /// Get name of the trigger of the first decision of the current run
+    pub fn get_trigger(&self) -> Option<&str> {
+        self.emu.get_trigger()
+    }
+
+    /// Get address of the instruction which wrote the first decision of the current run
+    ///
+    /// Together with the debug information this shows the code path reached by an attack
+    pub fn get_trigger_pc(&self) -> Option<u64> {
+        self.emu.get_trigger_pc()
+    }
+
+    /// Get number of executed instructions of the current run (requires `count_instructions`)
+    ///
+    /// Shows how far an attack ran, e.g. to distinguish early crashes
+    pub fn get_instruction_count(&self) -> usize {
+        self.emu.get_instruction_count()
+    }
+
+    /// Get number of clock cycles of the last run (requires `count_cycles`)
+    pub fn cycles_executed(&self) -> usize {
+        self.emu.cycles_executed()
+    }
+
+    /// Get lowest stack pointer of the last run (requires `track_stack_usage`)
+    pub fn min_sp_reached(&self) -> Option<u64> {
+        self.emu.min_sp_reached()
+    }
+
+    /// Get maximum stack usage in bytes of the last run (requires `track_stack_usage`)
+    ///
+    /// Compared with the stack size this shows if a fault causes abnormal stack growth
+    pub fn max_stack_usage(&self) -> Option<u64> {
+        self.emu.max_stack_usage()
+    }
+
+    /// Get fingerprint of the trace and the state of the last run
+    ///
+    /// The hash depends on the order of the executed addresses and includes the
+    /// final run state. Use it after `run_with_faults` with `RecordFullTrace` and
+    /// deep analysis, as reduced traces lose the order of the program flow
+    pub fn trace_fingerprint(&self) -> u64 {
+        self.emu.trace_fingerprint()
+    }
+
+    /// Save the current state of the simulation
+    pub fn snapshot(&self) -> Result<Snapshot, uc_error> {
+        self.emu.snapshot()
+    }
+
+    /// Restore a saved state of the simulation
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), uc_error> {
+        self.emu.restore(snapshot)
+    }
+
+    /// Execute the given number of instructions and add the time to the run duration
+    pub fn run_steps(&mut self, cycles: usize) -> StopReason {
+        let start = Instant::now();
+        let stop_reason = self.emu.run_steps(cycles, false);
+        self.run_duration += start.elapsed();
+        stop_reason
+    }
+
+    /// Execute until the program counter reaches the target address
+    ///
+    /// Returns true if the target was reached before the step limit
+    pub fn run_until_address(&mut self, target: u64, max_steps: usize) -> Result<bool, uc_error> {
+        let start = Instant::now();
+        let reached = self.emu.run_until_address(target, max_steps);
+        self.run_duration += start.elapsed();
+        reached
     }
 
     /// Setup system state to a successful or failed state
     /// and run the program. Return the state of the program after compilation
-    fn run(&mut self, cycles: usize, run_successful: bool) -> RunState {
+    fn run(&mut self, run_successful: bool) -> RunState {
         // Initial and load program
         self.init_and_load(run_successful);
         // Start execution with the given amount of instructions
-        let ret_info = self.emu.run_steps(cycles, false);
+        let ret_info = self.emu.run_steps(self.instruction_limit, false);
 
-        info!("Program stopped successful {:?}", ret_info);
+        info!("Program stopped with {:?}", ret_info);
         // Return emulation state
         self.emu.get_state()
     }
@@ -64,19 +354,27 @@ impl<'a> Control<'a> {
 
     /// Check if code under investigation is working correct for
     /// positive and negative execution
-    pub fn check_program(&mut self, cycles: usize) -> Result<(), String> {
+    ///
+    /// The error contains the path which failed and the observed state
+    pub fn verify_program(&mut self) -> Result<(), ProgramCheckError> {
         // Deactivate io print
-        self.emu.deactivate_printf_function();
-        if self.run(cycles, true) != RunState::Success {
-            return Err(
-                "Program function check failed. Success path is not working properly!".to_string(),
-            );
+        if self.emu.get_config().deactivate_print {
+            self.emu.deactivate_printf_function();
         }
-        if self.run(cycles, false) != RunState::Failed {
-            return Err(
-                "Program function check failed. Failure path is not working properly!".to_string(),
-            );
+        match self.run(true) {
+            RunState::Success => (),
+            state => return Err(ProgramCheckError::SuccessPath(state)),
+        }
+        match self.run(false) {
+            RunState::Failed => (),
+            state => return Err(ProgramCheckError::FailurePath(state)),
         }
+        Ok(())
+    }
+
+    /// Check the program with `verify_program` and print the result
+    pub fn check_program(&mut self) -> Result<(), String> {
+        self.verify_program().map_err(|error| error.to_string())?;
         println!("Program checked successfully");
         Ok(())
     }
@@ -86,16 +384,15 @@ impl<'a> Control<'a> {
     /// If code tracing was activated a vector array with the trace records will be returned
     pub fn run_with_faults(
         &mut self,
-        cycles: usize,
         run_type: RunType,
         deep_analysis_trace: bool,
         faults: &[FaultRecord],
     ) -> Result<Data, String> {
-        let mut restore_required = false;
+        // Original memory of faults which modify the code (address, data)
+        let mut restore_list: Vec<(u64, Vec<u8>)> = Vec::new();
+        self.run_duration = Duration::ZERO;
         // Initialize and load
-        self.init_and_load(false);
-        // Deactivate io print
-        self.emu.deactivate_printf_function();
+        self.start();
 
         match run_type {
             RunType::RecordTrace => {
@@ -111,29 +408,39 @@ impl<'a> Control<'a> {
             _ => (),
         }
 
-        // Preload instruction backup if fault is inserted with index = 0
-        let (mut address, mut instruction) = self.emu.asm_cmd_read();
         // Iterate over all faults and run the program step by step
         for fault in faults {
             if fault.index != 0 {
                 // One single step
-                if self.emu.run_steps(1, false).is_err() {
+                if self.run_steps(1).is_error() {
                     return Ok(Data::None);
                 }
                 // Restore instruction if required
-                if restore_required {
-                    self.emu.asm_cmd_write(address, &instruction).unwrap();
-                    restore_required = false;
-                }
+                self.restore_memory(&mut restore_list);
                 // Execute remaining steps
-                if fault.index != 1 && self.emu.run_steps(fault.index - 1, false).is_err() {
+                if fault.index != 1 && self.run_steps(fault.index - 1).is_error() {
                     return Ok(Data::None);
                 }
-                // Read instruction for later restore
-                (address, instruction) = self.emu.asm_cmd_read();
             }
-            // Inject fault
-            restore_required |= self.emu.execute_fault_injection(fault);
+            // A previous fault may have moved the program counter out of the code,
+            // the attack is not successful then
+            let address = self.emu.get_program_counter();
+            if !self.emu.is_code_address(address) {
+                debug!(
+                    "Fault address 0x{:X} out of range of the loaded code ({:?})",
+                    address, fault.fault_type
+                );
+                return Ok(Data::None);
+            }
+            // Inject fault and keep the original memory for later restore
+            if self.emu.execute_fault_injection(fault)? {
+                if let Some(fault_data) = self.emu.get_fault_data().last() {
+                    restore_list.push((
+                        fault_data.record.address(),
+                        fault_data.original_instruction.clone(),
+                    ));
+                }
+            }
         }
 
         // Start tracing or check previous run state
@@ -150,13 +457,13 @@ impl<'a> Control<'a> {
         }
 
         // Run to completion
-        if restore_required {
-            if self.emu.run_steps(1, false).is_err() {
+        if !restore_list.is_empty() {
+            if self.run_steps(1).is_error() {
                 return Ok(Data::None);
             }
-            self.emu.asm_cmd_write(address, &instruction).unwrap();
+            self.restore_memory(&mut restore_list);
         }
-        if self.emu.run_steps(cycles, false).is_err() {
+        if self.run_steps(self.instruction_limit).is_error() {
             return Ok(Data::None);
         }
 
@@ -173,12 +480,156 @@ impl<'a> Control<'a> {
             }
             RunType::Run => {
                 // Check if fault attack was successful if yes return faults
+                // with the final register state and the run duration
                 if self.emu.get_state() == RunState::Success {
-                    Ok(Data::Fault(self.emu.get_fault_data().clone()))
+                    let final_registers = self.emu.registers_read();
+                    let trigger = self.emu.get_trigger().map(str::to_string);
+                    let mut fault_data = self.emu.get_fault_data().clone();
+                    fault_data.iter_mut().for_each(|data| {
+                        data.final_registers = final_registers;
+                        data.trigger = trigger.clone();
+                        data.run_duration = self.run_duration;
+                    });
+                    Ok(Data::Fault(fault_data))
                 } else {
                     Ok(Data::None)
                 }
             }
         }
     }
+
+    /// Record the trace of a run with faults and return emulation errors
+    ///
+    /// `run_with_faults` returns no data if the run stops with an error, which looks
+    /// like an empty trace. Here the stop reason of the failed run is returned as error
+    pub fn try_record_trace(
+        &mut self,
+        run_type: RunType,
+        deep_analysis_trace: bool,
+        faults: &[FaultRecord],
+    ) -> Result<Vec<TraceRecord>, String> {
+        if run_type == RunType::Run {
+            return Err("No trace is recorded with RunType::Run".to_string());
+        }
+        match self.run_with_faults(run_type, deep_analysis_trace, faults)? {
+            Data::Trace(trace) => Ok(trace),
+            _ => Err(format!(
+                "Trace run stopped with {:?} at 0x{:X}",
+                self.get_stop_reason(),
+                self.emu.get_program_counter()
+            )),
+        }
+    }
+
+    /// Replay an attack in single steps with verbose logging
+    ///
+    /// The faults are reconstructed from the fault data of the attack and injected
+    /// like in `run_with_faults`. For each step the program counter and the changed
+    /// registers (32 bit ARM targets) are logged. Returns the state at the end of the run
+    pub fn replay(&mut self, fault_data: &[FaultData]) -> Result<RunState, String> {
+        let faults = FaultData::get_simulation_fault_records(fault_data);
+        let mut restore_list: Vec<(u64, Vec<u8>)> = Vec::new();
+        let mut registers = None;
+        self.run_duration = Duration::ZERO;
+        self.start();
+
+        for fault in &faults {
+            if !self.replay_steps(fault.index, &mut restore_list, &mut registers) {
+                return Ok(self.emu.get_state());
+            }
+            info!(
+                "0x{:08X}: {:?}",
+                self.emu.get_program_counter(),
+                fault.fault_type
+            );
+            if self.emu.execute_fault_injection(fault)? {
+                if let Some(fault_data) = self.emu.get_fault_data().last() {
+                    restore_list.push((
+                        fault_data.record.address(),
+                        fault_data.original_instruction.clone(),
+                    ));
+                }
+            }
+        }
+        self.replay_steps(self.instruction_limit, &mut restore_list, &mut registers);
+
+        info!("Replay stopped with {:?}", self.emu.get_stop_reason());
+        Ok(self.emu.get_state())
+    }
+
+    /// Execute single steps and log the program counter and register changes
+    ///
+    /// Returns false if the run stopped (error, end address or auth address written)
+    fn replay_steps(
+        &mut self,
+        steps: usize,
+        restore_list: &mut Vec<(u64, Vec<u8>)>,
+        registers: &mut Option<[u64; 17]>,
+    ) -> bool {
+        for _ in 0..steps {
+            let address = self.emu.get_program_counter();
+            let stop_reason = self.run_steps(1);
+            // Faulted code is only executed once
+            self.restore_memory(restore_list);
+
+            let current = self.emu.registers_read();
+            let changes: Vec<String> = match (*registers, current) {
+                (Some(previous), Some(current)) => ARM_REG
+                    .iter()
+                    .zip(previous.iter().zip(current.iter()))
+                    .filter(|(_, (previous, current))| previous != current)
+                    .map(|(register, (previous, current))| {
+                        format!("{:?}: 0x{:08X} -> 0x{:08X}", register, previous, current)
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            info!("0x{:08X}: {}", address, changes.join(", "));
+            *registers = current;
+
+            if stop_reason.is_error()
+                || stop_reason == StopReason::EndAddress
+                || self.emu.get_state() != RunState::Init
+            {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Write back the original memory of all code modifying faults
+    ///
+    /// Restore is done in reverse order to handle multiple faults on the same address.
+    /// Debug builds check that the original code is back in memory
+    fn restore_memory(&mut self, restore_list: &mut Vec<(u64, Vec<u8>)>) {
+        let restored = !restore_list.is_empty();
+        for (address, data) in restore_list.drain(..).rev() {
+            self.emu.asm_cmd_write(address, &data).unwrap();
+        }
+        if cfg!(debug_assertions) && restored {
+            self.check_restored_memory();
+        }
+    }
+
+    /// Compare code of all faults of the run with the original instructions
+    ///
+    /// Only the first fault of an address holds the original code
+    fn check_restored_memory(&mut self) {
+        let mut checked = HashSet::new();
+        for fault_data in self.emu.get_fault_data().clone() {
+            let address = fault_data.record.address();
+            if fault_data.modified_instruction.is_empty() || !checked.insert(address) {
+                continue;
+            }
+            let mut memory = vec![0; fault_data.original_instruction.len()];
+            match self.emu.memory_read(address, &mut memory) {
+                Ok(()) if memory == fault_data.original_instruction => (),
+                Ok(()) => error!(
+                    "Code at 0x{:X} not restored after {:?}: {:02x?} instead of {:02x?}",
+                    address, fault_data.fault.fault_type, memory, fault_data.original_instruction
+                ),
+                Err(e) => error!("Failed to read code at 0x{:X}: {:?}", address, e),
+            }
+        }
+    }
 }
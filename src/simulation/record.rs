@@ -1,64 +1,17 @@
+use crate::data;
 use crate::fault_attacks::faults::FaultType;
-use std::hash::{Hash, Hasher};
 
-#[derive(Clone, Debug)]
-/// Representation for a fault which shall be executed at step `index` of a simulation.
-pub struct FaultRecord {
-    pub index: usize,
-    pub fault_type: FaultType,
-}
-
-#[derive(Clone, Debug, Eq)]
-/// One recorded step of a simulation
-pub enum TraceRecord {
-    Instruction {
-        address: u64,
-        index: usize,
-        asm_instruction: Vec<u8>,
-        registers: Option<[u32; 17]>,
-    },
-    Fault {
-        address: u64,
-        fault_type: String,
-        data: Vec<u8>,
-    },
-}
-
-impl PartialEq for TraceRecord {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (
-                TraceRecord::Instruction {
-                    address: address1,
-                    index: _,
-                    asm_instruction: _,
-                    registers: _,
-                },
-                TraceRecord::Instruction {
-                    address: address2,
-                    index: _,
-                    asm_instruction: _,
-                    registers: _,
-                },
-            ) => address1 == address2,
-            _ => false,
-        }
-    }
-}
+pub use crate::data::{TraceRecord, WatchRecord, WriteRecord};
 
-impl Hash for TraceRecord {
-    fn hash<H: Hasher>(&self, hasher: &mut H) {
-        if let TraceRecord::Instruction { address, .. } = self {
-            address.hash(hasher);
-        }
-    }
-}
+/// Fault record of a simulation with the executable fault type
+pub type FaultRecord = data::FaultRecord<FaultType>;
 
-impl TraceRecord {
-    pub fn address(&self) -> u64 {
-        match self {
-            TraceRecord::Instruction { address, .. } => *address,
-            TraceRecord::Fault { address, .. } => *address,
+impl FaultRecord {
+    /// Get a data-only copy of the record with the name of the fault type
+    pub fn descriptor(&self) -> data::FaultRecord<String> {
+        data::FaultRecord {
+            index: self.index,
+            fault_type: self.fault_type.to_string(),
         }
     }
 }
@@ -0,0 +1,143 @@
+use crate::simulation::{
+    fault_data::FaultData,
+    record::{FaultRecord, TraceRecord},
+};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// Convert byte vector to a hex string (e.g. "00bf")
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Serialize fault record with the fault type as readable string
+impl Serialize for FaultRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("FaultRecord", 2)?;
+        state.serialize_field("index", &self.index)?;
+        state.serialize_field("fault_type", &format!("{:?}", self.fault_type))?;
+        state.end()
+    }
+}
+
+/// Serialize fault data with address, size and hex encoded instructions
+impl Serialize for FaultData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let description = match &self.record {
+            TraceRecord::Fault { fault_type, .. } => fault_type.clone(),
+            TraceRecord::Instruction { .. } => String::new(),
+        };
+
+        let mut state = serializer.serialize_struct("FaultData", 11)?;
+        state.serialize_field("address", &format!("0x{:08x}", self.record.address()))?;
+        state.serialize_field("size", &self.original_instruction.len())?;
+        state.serialize_field("instruction_size", &self.instruction_size)?;
+        state.serialize_field("fault", &self.fault)?;
+        state.serialize_field("description", &description)?;
+        state.serialize_field("original_instruction", &to_hex(&self.original_instruction))?;
+        state.serialize_field("modified_instruction", &to_hex(&self.modified_instruction))?;
+        state.serialize_field("original_register_value", &self.original_register_value)?;
+        state.serialize_field("final_registers", &self.final_registers)?;
+        state.serialize_field("trigger", &self.trigger)?;
+        state.serialize_field("run_duration_us", &(self.run_duration.as_micros() as u64))?;
+        state.end()
+    }
+}
+
+/// Export structure of all successful attacks
+pub struct FaultExport<'a> {
+    pub seed: Option<u64>,
+    pub attacks: &'a [Vec<FaultData>],
+}
+
+impl Serialize for FaultExport<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("FaultExport", 3)?;
+        state.serialize_field("seed", &self.seed)?;
+        state.serialize_field("count", &self.attacks.len())?;
+        state.serialize_field("attacks", &self.attacks)?;
+        state.end()
+    }
+}
+
+/// Progress of a checkpointed fault campaign
+///
+/// `next` is the number of processed attacks, `successful` holds the positions
+/// of the successful attacks in the attack list
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub total: usize,
+    pub next: usize,
+    pub successful: Vec<usize>,
+}
+
+impl Serialize for Checkpoint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Checkpoint", 3)?;
+        state.serialize_field("total", &self.total)?;
+        state.serialize_field("next", &self.next)?;
+        state.serialize_field("successful", &self.successful)?;
+        state.end()
+    }
+}
+
+impl Checkpoint {
+    /// Create a checkpoint for a campaign without processed attacks
+    pub fn new(total: usize) -> Self {
+        Self {
+            total,
+            ..Default::default()
+        }
+    }
+
+    /// Read checkpoint from a JSON file
+    pub fn read(path: &std::path::Path) -> Result<Self, String> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let value: serde_json::Value = serde_json::from_reader(std::io::BufReader::new(file))
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+        let number = |value: &serde_json::Value| {
+            value
+                .as_u64()
+                .map(|number| number as usize)
+                .ok_or_else(|| format!("Invalid checkpoint value: {}", value))
+        };
+        let checkpoint = Self {
+            total: number(&value["total"])?,
+            next: number(&value["next"])?,
+            successful: value["successful"]
+                .as_array()
+                .ok_or("Checkpoint without successful attacks")?
+                .iter()
+                .map(number)
+                .collect::<Result<_, _>>()?,
+        };
+        // Successful attacks have to be part of the processed attacks
+        if checkpoint.next > checkpoint.total
+            || checkpoint
+                .successful
+                .iter()
+                .any(|index| *index >= checkpoint.next)
+        {
+            return Err(format!("Inconsistent checkpoint {}", path.display()));
+        }
+        Ok(checkpoint)
+    }
+
+    /// Write checkpoint to a JSON file
+    ///
+    /// The data is written to a temporary file first which replaces the checkpoint,
+    /// so an interruption never leaves a partly written checkpoint behind
+    pub fn write(&self, path: &std::path::Path) -> Result<(), String> {
+        let temp_path = path.with_extension("tmp");
+        let file = std::fs::File::create(&temp_path)
+            .map_err(|e| format!("Failed to create {}: {}", temp_path.display(), e))?;
+        let mut writer = std::io::BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self)
+            .map_err(|e| format!("Failed to write checkpoint: {}", e))?;
+        std::io::Write::flush(&mut writer)
+            .map_err(|e| format!("Failed to write checkpoint: {}", e))?;
+        std::fs::rename(&temp_path, path)
+            .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+    }
+}
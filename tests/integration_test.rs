@@ -114,3 +114,1103 @@ fn run_fault_simulation_two_glitches() {
         _ => false,
     }));
 }
+
+#[test]
+/// Test for repeated execution on one simulation instance
+///
+/// This test runs the same successful attack twice on one simulation instance
+/// and checks if the resulting fault data is identical
+fn run_same_attack_twice() {
+    env::set_var("RAYON_NUM_THREADS", "1");
+    let mut attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let result = attack
+        .fault_simulation(2000, &[Glitch::new(1)], false, false)
+        .unwrap();
+    let fault_records = FaultData::get_simulation_fault_records(&result[0]);
+
+    let mut simulation = Control::new(&attack.file_data, &attack.config).unwrap();
+    simulation.set_instruction_limit(2000);
+    let mut runs = Vec::new();
+    for _ in 0..2 {
+        match simulation
+            .run_with_faults(RunType::Run, false, &fault_records)
+            .unwrap()
+        {
+            Data::Fault(fault_data) => runs.push(fault_data),
+            _ => panic!("Attack was not successful"),
+        }
+    }
+
+    // Check for identical fault data
+    assert_eq!(runs[0].len(), runs[1].len());
+    for (first, second) in runs[0].iter().zip(runs[1].iter()) {
+        assert_eq!(first.record.address(), second.record.address());
+        assert_eq!(first.fault.index, second.fault.index);
+        assert_eq!(first.original_instruction, second.original_instruction);
+        assert_eq!(first.modified_instruction, second.modified_instruction);
+        assert_eq!(first.final_registers, second.final_registers);
+    }
+}
+
+#[test]
+/// Test for trace hook range
+///
+/// This test records the trace of victim_.elf and checks that instructions in the last
+/// bytes of the code (main) are traced and no address outside the code segment is recorded
+fn trace_covers_end_of_code() {
+    let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let (segment, _) = attack
+        .file_data
+        .program_data
+        .iter()
+        .find(|(segment, _)| segment.p_flags & elf::abi::PF_X != 0)
+        .unwrap();
+    let code = segment.p_paddr..segment.p_paddr + segment.p_filesz;
+    // Constant data follows the code in the segment, main is the last function of .text
+    let text = &attack.file_data.section_map[".text"];
+    let text_end = text.sh_addr + text.sh_size;
+    assert!(code.contains(&(text_end - 1)));
+
+    let trace = attack.annotated_trace(2000).unwrap();
+    assert!(trace
+        .iter()
+        .any(|(address, function, _)| function.starts_with("main") && *address >= text_end - 0x40));
+    assert!(trace.iter().all(|(address, _, _)| code.contains(address)));
+}
+
+#[test]
+/// Test for faults relative to a symbol
+///
+/// This test walks the mixed 2 and 4 byte instructions of main in victim_.elf and
+/// creates a fault at an instruction behind the call of decision_activation
+fn fault_at_symbol_offset() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let config = SimulationConfig::default();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation.start();
+    // push (2), add (2), bl (4), ldr (2), adds (2), ldr.w (4)
+    assert_eq!(
+        simulation.instruction_address(0x8000628, 3).unwrap(),
+        0x8000630
+    );
+    assert_eq!(
+        simulation.instruction_address(0x8000628, 6).unwrap(),
+        0x8000638
+    );
+
+    let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let glitch = get_fault_from("glitch_1").unwrap();
+    let start = attack.fault_at_symbol(2000, "main", &glitch).unwrap();
+    let record = attack
+        .fault_at_symbol_offset(2000, "main", 6, &glitch)
+        .unwrap();
+    assert!(record.index > start.index + 6);
+}
+
+#[test]
+/// Test for the attacks of an address range
+///
+/// This test creates glitch attacks for the first instructions of main in victim_.elf,
+/// which are a mix of 2 and 4 byte instructions, and checks their fault records
+fn faults_in_address_range() {
+    let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let glitch = get_fault_from("glitch_1").unwrap();
+    // push (2), add (2), bl (4), ldr (2), adds (2), ldr.w (4)
+    let attacks = attack
+        .faults_in_range(2000, 0x8000628, 0x8000638, &glitch)
+        .unwrap();
+    assert_eq!(attacks.len(), 6);
+    assert!(attacks.iter().all(|attack| attack.len() == 1));
+    assert_eq!(
+        attacks[0][0].index,
+        attack.fault_at_symbol(2000, "main", &glitch).unwrap().index
+    );
+    assert_eq!(
+        attacks[5][0].index,
+        attack
+            .fault_at_symbol_offset(2000, "main", 5, &glitch)
+            .unwrap()
+            .index
+    );
+}
+
+#[test]
+/// Test for the run states of all attacks
+///
+/// This test glitches the first instructions of main in victim_.elf and checks that
+/// failed attacks are only returned with `collect_all`
+fn collect_all_attack_states() {
+    let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let glitch = get_fault_from("glitch_1").unwrap();
+    let attacks = attack
+        .faults_in_range(2000, 0x8000628, 0x8000638, &glitch)
+        .unwrap();
+
+    let all = attack.run_faults_with_state(2000, &attacks, true).unwrap();
+    assert_eq!(all.len(), attacks.len());
+    assert!(all.iter().all(|(fault_data, _)| fault_data.len() == 1));
+    assert_eq!(all[5].1, RunState::Success);
+    assert!(all.iter().any(|(_, state)| *state != RunState::Success));
+
+    let successful = attack.run_faults_with_state(2000, &attacks, false).unwrap();
+    assert!(successful
+        .iter()
+        .all(|(_, state)| *state == RunState::Success));
+    assert_eq!(
+        successful.len(),
+        all.iter()
+            .filter(|(_, state)| *state == RunState::Success)
+            .count()
+    );
+}
+
+#[test]
+/// Test for the comparison of two builds
+///
+/// This test glitches the first instructions of main in victim_.elf and the hardened
+/// victim_4.elf and checks that the successful attacks are only found in victim_.elf
+fn compare_hardened_build() {
+    let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let hardened = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_4.elf")).unwrap();
+    let attacks: Vec<Vec<SymbolFault>> = (0..10)
+        .map(|offset| {
+            vec![SymbolFault {
+                symbol: "main".to_string(),
+                offset,
+                fault_type: Glitch::new(1),
+            }]
+        })
+        .collect();
+
+    let comparison = FaultAttacks::compare_campaigns(&attack, &hardened, 2000, &attacks).unwrap();
+    // Glitch at 0x8000634
+    assert!(comparison.only_a.contains(&5));
+    assert!(comparison.only_b.is_empty());
+
+    let comparison = FaultAttacks::compare_campaigns(&attack, &attack, 2000, &attacks).unwrap();
+    assert_eq!(comparison, CampaignComparison::default());
+}
+
+#[test]
+/// Test for byte set faults at the end of an instruction
+///
+/// This test sets byte regions on victim_.elf which exceed the faulted instruction and
+/// checks that only the bytes of the instruction are modified
+fn byte_set_limited_to_instruction() {
+    let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let glitch = get_fault_from("glitch_1").unwrap();
+    let index = attack
+        .fault_at_symbol_offset(2000, "main", 5, &glitch)
+        .unwrap()
+        .index;
+    let attacks: Vec<Vec<FaultRecord>> = [ByteSet::new(0, 0xff, 8), ByteSet::new(4, 0xff, 2)]
+        .into_iter()
+        .map(|fault_type| {
+            vec![FaultRecord {
+                index,
+                fault_type: fault_type as FaultType,
+            }]
+        })
+        .collect();
+
+    let result = attack.run_faults_with_state(2000, &attacks, true).unwrap();
+    let fault = &result[0].0[0];
+    assert_eq!(fault.modified_instruction.len(), fault.instruction_size);
+    assert_eq!(fault.original_instruction.len(), fault.instruction_size);
+    // Instructions have at most 4 bytes, so the second region is outside
+    assert!(result[1].0.is_empty());
+}
+
+/// Custom fault which skips the instruction at the fault address
+#[derive(Debug)]
+struct SkipFault;
+
+impl FaultFunctions for SkipFault {
+    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> Result<bool, String> {
+        let (address, original_instruction) = cpu.asm_cmd_read();
+        cpu.set_program_counter(address + original_instruction.len() as u64);
+        let record = TraceRecord::Fault {
+            address,
+            fault_type: format!("{:?}", self),
+            data: original_instruction.clone(),
+        };
+        cpu.get_trace_data().push(record.clone());
+        cpu.get_fault_data().push(FaultData::new(
+            record,
+            fault.clone(),
+            original_instruction,
+            vec![],
+        ));
+        Ok(false)
+    }
+
+    fn filter(&self, _records: &mut Vec<TraceRecord>, _cs: &Disassembly) {}
+
+    fn try_from(&self, _input: &str) -> Option<FaultType> {
+        None
+    }
+
+    fn get_list(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn name(&self) -> &'static str {
+        "SkipFault"
+    }
+}
+
+/// Custom fault which moves the program counter into the RAM
+#[derive(Debug)]
+struct JumpToRam;
+
+impl FaultFunctions for JumpToRam {
+    fn execute(&self, cpu: &mut Cpu, fault: &FaultRecord) -> Result<bool, String> {
+        let (address, original_instruction) = cpu.asm_cmd_read();
+        cpu.set_program_counter(0x2000_0000);
+        let record = TraceRecord::Fault {
+            address,
+            fault_type: format!("{:?}", self),
+            data: original_instruction.clone(),
+        };
+        cpu.get_trace_data().push(record.clone());
+        cpu.get_fault_data().push(FaultData::new(
+            record,
+            fault.clone(),
+            original_instruction,
+            vec![],
+        ));
+        Ok(false)
+    }
+
+    fn filter(&self, _records: &mut Vec<TraceRecord>, _cs: &Disassembly) {}
+
+    fn try_from(&self, _input: &str) -> Option<FaultType> {
+        None
+    }
+
+    fn get_list(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn name(&self) -> &'static str {
+        "JumpToRam"
+    }
+}
+
+#[test]
+/// Test for faults outside of the code in a campaign
+///
+/// This test runs a double attack on victim_.elf whose first fault moves the program
+/// counter into the RAM and checks that the campaign continues with the next attack
+fn fault_out_of_code_in_campaign() {
+    let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let glitch = get_fault_from("glitch_1").unwrap();
+    let successful = attack
+        .fault_at_symbol_offset(2000, "main", 5, &glitch)
+        .unwrap();
+    let jump = FaultRecord {
+        index: successful.index,
+        fault_type: std::sync::Arc::new(JumpToRam),
+    };
+    // Second fault is injected at the new program counter
+    let out_of_code = FaultRecord {
+        index: 0,
+        fault_type: glitch.clone(),
+    };
+    let attacks = vec![vec![jump, out_of_code], vec![successful.clone()]];
+
+    let result = attack.run_faults_parallel(2000, &attacks, 1).unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][0].fault.index, successful.index);
+}
+
+#[test]
+/// Test for data faults on read-only memory in reused instances
+///
+/// This test flips the literal of the decision data address in the flash of victim_.elf
+/// and checks that a following nominal run on the same instance equals a fresh instance
+fn reuse_instance_after_flash_data_fault() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let config = SimulationConfig::default();
+    let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    // Literal pool of main holding the address of decisiondata (0x20000004)
+    let databf: FaultType = DataBitFlip::new(0x8000660, 0x1000_0000);
+    let record = attack
+        .fault_at_symbol_offset(2000, "main", 0, &databf)
+        .unwrap();
+
+    let mut fresh = Control::new(&file_data, &config).unwrap();
+    fresh.run_with_faults(RunType::Run, false, &[]).unwrap();
+
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    let _ = simulation.run_with_faults(RunType::Run, false, &[record]);
+    assert_ne!(
+        simulation.read_memory(0x8000660, 4).unwrap(),
+        [0x04, 0x00, 0x00, 0x20]
+    );
+    simulation
+        .run_with_faults(RunType::Run, false, &[])
+        .unwrap();
+    assert_eq!(
+        simulation.read_memory(0x8000660, 4).unwrap(),
+        [0x04, 0x00, 0x00, 0x20]
+    );
+    assert_eq!(simulation.get_state(), fresh.get_state());
+    assert_eq!(simulation.get_stop_reason(), fresh.get_stop_reason());
+}
+
+#[test]
+/// Test for cancelled campaigns
+///
+/// This test cancels a campaign on victim_.elf after the first attack and checks that
+/// the remaining attacks are skipped. The next campaign starts with a reset counter
+fn cancel_campaign() {
+    let mut attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let glitch = get_fault_from("glitch_1").unwrap();
+    let successful = attack
+        .fault_at_symbol_offset(2000, "main", 5, &glitch)
+        .unwrap();
+    let attacks = vec![vec![successful]; 3];
+
+    let cancel = attack.cancel_flag();
+    attack.set_progress_callback(move |_, _| {
+        cancel.store(true, std::sync::atomic::Ordering::Relaxed)
+    });
+    let result = attack.run_faults_parallel(2000, &attacks, 1).unwrap();
+    assert_eq!(result.len(), 1);
+    let counter = attack.success_counter();
+    assert_eq!(counter.load(std::sync::atomic::Ordering::Relaxed), 1);
+
+    attack.set_progress_callback(|_, _| {});
+    let result = attack.run_faults_parallel(2000, &attacks, 1).unwrap();
+    assert_eq!(result.len(), 3);
+    assert_eq!(counter.load(std::sync::atomic::Ordering::Relaxed), 3);
+}
+
+#[test]
+/// Test for campaign counters of sequential attack runs
+///
+/// This test checks the success counter and progress of a run on victim_.elf which
+/// stops at the first successful attack
+fn sequential_campaign_counters() {
+    let mut attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let glitch = get_fault_from("glitch_1").unwrap();
+    let successful = attack
+        .fault_at_symbol_offset(2000, "main", 5, &glitch)
+        .unwrap();
+    let attacks = vec![vec![successful]; 3];
+    let counter = attack.success_counter();
+
+    let progress = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let calls = progress.clone();
+    attack
+        .set_progress_callback(move |current, total| calls.lock().unwrap().push((current, total)));
+    assert!(attack.run_faults_first(2000, &attacks).unwrap().is_some());
+    assert_eq!(*progress.lock().unwrap(), vec![(1, 3)]);
+    assert_eq!(counter.load(std::sync::atomic::Ordering::Relaxed), 1);
+}
+
+#[test]
+/// Test for the reduction of attacks
+///
+/// This test minimizes attacks on victim_.elf and checks that an attack which is not
+/// successful is rejected
+fn minimize_successful_attack() {
+    let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let glitch = get_fault_from("glitch_1").unwrap();
+    let successful = attack
+        .fault_at_symbol_offset(2000, "main", 5, &glitch)
+        .unwrap();
+    let minimized = attack
+        .minimize_attack(2000, std::slice::from_ref(&successful))
+        .unwrap();
+    assert_eq!(minimized.len(), 1);
+    assert_eq!(minimized[0].index, successful.index);
+
+    assert!(attack.minimize_attack(2000, &[]).is_err());
+}
+
+#[test]
+/// Test for the disassembly of fault data
+///
+/// This test glitches victim_.elf and checks that the fault data keeps the Thumb
+/// state of the target, so the instructions are decoded as Thumb code
+fn disassemble_fault_data_in_thumb_state() {
+    let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let glitch = get_fault_from("glitch_1").unwrap();
+    let successful = attack
+        .fault_at_symbol_offset(2000, "main", 5, &glitch)
+        .unwrap();
+    let result = attack
+        .run_faults_parallel(2000, &[vec![successful]], 1)
+        .unwrap();
+    let fault_data = &result[0][0];
+    assert!(fault_data.thumb);
+    assert_eq!(fault_data.target_arch, TargetArch::CortexM);
+
+    let (original, faulted) = fault_data.disassemble();
+    assert!(!original.is_empty());
+    assert!(faulted.contains("nop"), "{}", faulted);
+}
+
+#[test]
+/// Test for run durations of a campaign
+///
+/// This test profiles successful attacks on victim_.elf and checks that the durations
+/// are returned for every attack and are part of the fault data of the attack
+fn profile_attack_durations() {
+    let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let glitch = get_fault_from("glitch_1").unwrap();
+    let successful = attack
+        .fault_at_symbol_offset(2000, "main", 5, &glitch)
+        .unwrap();
+    let attacks = vec![vec![successful]; 3];
+
+    let durations = attack.profile_attacks(2000, &attacks).unwrap();
+    assert_eq!(durations.len(), 3);
+    assert!(durations
+        .iter()
+        .all(|duration| duration.is_some_and(|duration| !duration.is_zero())));
+    let counter = attack.success_counter();
+    assert_eq!(counter.load(std::sync::atomic::Ordering::Relaxed), 3);
+
+    let result = attack.run_faults_parallel(2000, &attacks, 1).unwrap();
+    assert!(result
+        .iter()
+        .flatten()
+        .all(|fault_data| !fault_data.run_duration.is_zero()));
+}
+
+#[test]
+/// Test for user defined fault types
+///
+/// This test injects a custom fault into victim_.elf and checks that its fault data
+/// is recorded in the trace
+fn run_custom_fault() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let config = SimulationConfig::default();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    let fault = FaultRecord {
+        index: 10,
+        fault_type: std::sync::Arc::new(SkipFault),
+    };
+    let data = simulation
+        .run_with_faults(RunType::RecordFullTrace, true, &[fault])
+        .unwrap();
+    match data {
+        Data::Trace(trace) => assert!(trace
+            .iter()
+            .any(|record| matches!(record, TraceRecord::Fault { fault_type, .. } if fault_type == "SkipFault"))),
+        _ => panic!("No trace recorded"),
+    }
+}
+
+#[test]
+/// Test for the executed instruction counter
+///
+/// This test runs victim_.elf without faults and checks that the counted instructions
+/// are in the range of the instruction limit. Without `count_instructions` nothing is counted
+fn count_executed_instructions() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let config = SimulationConfig::default();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation
+        .run_with_faults(RunType::Run, false, &[])
+        .unwrap();
+    assert_eq!(simulation.get_instruction_count(), 0);
+
+    let config = SimulationBuilder::new()
+        .count_instructions(true)
+        .build_config()
+        .unwrap();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation
+        .run_with_faults(RunType::Run, false, &[])
+        .unwrap();
+    let count = simulation.get_instruction_count();
+    assert!(count > 0 && count <= DEFAULT_INSTRUCTION_LIMIT, "{}", count);
+}
+
+#[test]
+/// Test for the simulation builder
+///
+/// This test builds a ready simulation of victim_.elf and checks that the
+/// instruction limit of the builder is not exceeded by the runs
+fn build_simulation_with_instruction_limit() {
+    let attack = SimulationBuilder::new()
+        .elf(std::path::PathBuf::from("tests/bin/victim_.elf"))
+        .instruction_limit(10)
+        .count_instructions(true)
+        .build()
+        .unwrap();
+    assert_eq!(attack.config.target_arch, TargetArch::CortexM);
+
+    let mut simulation = Control::new(&attack.file_data, &attack.config).unwrap();
+    simulation.set_instruction_limit(DEFAULT_INSTRUCTION_LIMIT);
+    simulation
+        .run_with_faults(RunType::Run, false, &[])
+        .unwrap();
+    let count = simulation.get_instruction_count();
+    assert!(count > 0 && count <= 10, "{}", count);
+}
+
+#[test]
+/// Test for trace recording with error results
+///
+/// This test records the trace of victim_.elf and checks that a run which can not
+/// execute the code returns its stop reason instead of an empty trace
+fn record_trace_with_errors() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let config = SimulationConfig::default();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    let trace = simulation
+        .try_record_trace(RunType::RecordTrace, true, &[])
+        .unwrap();
+    assert!(!trace.is_empty());
+    assert!(simulation
+        .try_record_trace(RunType::Run, true, &[])
+        .is_err());
+
+    // Code without execute permission
+    let config = SimulationConfig {
+        code_permission: Some(Permission::READ),
+        ..Default::default()
+    };
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    let error = simulation
+        .try_record_trace(RunType::RecordTrace, true, &[])
+        .unwrap_err();
+    assert!(error.contains("FETCH_PROT"), "{}", error);
+}
+
+#[test]
+/// Test for the access to the flags register
+///
+/// This test sets the zero flag of victim_.elf before the first instruction and
+/// checks that the Thumb state of the xPSR is kept
+fn set_condition_flags() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let config = SimulationConfig::default();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation.start();
+    let flags = simulation.read_flags().unwrap();
+    simulation.write_flags(flags | 1 << 30).unwrap();
+    assert_eq!(simulation.read_flags().unwrap(), flags | 1 << 30);
+    // Thumb bit of the EPSR
+    assert_ne!(simulation.read_flags().unwrap() & 1 << 24, 0);
+}
+
+#[test]
+/// Test for memory watchpoints
+///
+/// This test watches the top of the stack of victim_.elf, which is written by the
+/// first function calls, and checks the write log and the halt on the first change
+fn watch_stack_writes() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let stack = &file_data.section_map[".stack"];
+    let watch_base = stack.sh_addr + stack.sh_size - 0x40;
+    let config = SimulationConfig::default();
+
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation.add_watchpoint(watch_base, 0x40, false).unwrap();
+    simulation.start();
+    simulation.run_steps(DEFAULT_INSTRUCTION_LIMIT);
+    let log = simulation.get_watch_log();
+    assert!(!log.is_empty());
+    assert!(log.iter().all(|record| record.address >= watch_base
+        && record.address < watch_base + 0x40
+        && record.old_data.len() == record.new_data.len()));
+
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation.add_watchpoint(watch_base, 0x40, true).unwrap();
+    simulation.start();
+    let stop_reason = simulation.run_steps(DEFAULT_INSTRUCTION_LIMIT);
+    let log = simulation.get_watch_log();
+    let last = log.last().unwrap();
+    assert_eq!(stop_reason, StopReason::Watchpoint(last.address));
+    assert_ne!(last.old_data, last.new_data);
+}
+
+#[test]
+/// Test for the log of all memory writes
+///
+/// This test runs victim_.elf with a limited write log and checks that the log is
+/// capped and contains the stack writes of the first function call
+fn log_memory_writes() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let stack = &file_data.section_map[".stack"];
+    let config = SimulationConfig::default();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation
+        .run_with_faults(RunType::Run, false, &[])
+        .unwrap();
+    assert!(simulation.get_write_log().is_empty());
+
+    let config = SimulationBuilder::new()
+        .write_log_limit(16)
+        .build_config()
+        .unwrap();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation
+        .run_with_faults(RunType::Run, false, &[])
+        .unwrap();
+    let log = simulation.get_write_log();
+    assert_eq!(log.len(), 16);
+    assert!(log
+        .iter()
+        .all(|record| (0x08000000..0x080006c6).contains(&record.pc)));
+    assert!(log.iter().any(|record| record.address >= stack.sh_addr
+        && record.address < stack.sh_addr + stack.sh_size
+        && record.size == 4));
+}
+
+#[test]
+/// Test for the streaming trace callback
+///
+/// This test counts the instructions of victim_.elf with a trace callback and compares
+/// them with the instruction counter of the simulation
+fn stream_trace_addresses() {
+    let count = std::cell::Cell::new(0);
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let config = SimulationBuilder::new()
+        .count_instructions(true)
+        .build_config()
+        .unwrap();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation
+        .set_trace_callback(|address| {
+            assert!((0x08000000..0x080006c6).contains(&address));
+            count.set(count.get() + 1);
+        })
+        .unwrap();
+    simulation
+        .run_with_faults(RunType::Run, false, &[])
+        .unwrap();
+    assert!(count.get() > 0);
+    assert_eq!(count.get(), simulation.get_instruction_count());
+}
+
+#[test]
+/// Test for the initialization of the Cortex-M system registers
+///
+/// This test sets the default system registers for victim_.elf and checks that VTOR
+/// points to the start of the code and the program still runs through
+fn init_system_registers() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let config = SimulationConfig {
+        system_registers: Some(SystemRegisters::default()),
+        ..Default::default()
+    };
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation.start();
+    assert_eq!(
+        simulation.read_memory(0xE000_ED08, 4).unwrap(),
+        0x08000000u32.to_le_bytes()
+    );
+    assert!(!simulation.run_steps(DEFAULT_INSTRUCTION_LIMIT).is_error());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+/// Test for attacks loaded from a JSON file
+///
+/// This test loads an attack with a symbol based and an address based fault for
+/// victim_.elf and compares the indices with the faults created by the api
+fn load_attacks_from_file() {
+    let path = env::temp_dir().join("fault_simulator_attacks.json");
+    std::fs::write(
+        &path,
+        r#"[[{"address": "0x08000638", "fault_type": "glitch_1", "count": 1},
+             {"symbol": "main", "offset": 3, "fault_type": "glitch_2", "count": 1,
+              "trigger_count": 1}]]"#,
+    )
+    .unwrap();
+
+    let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let attacks = attack.load_attacks(2000, &path).unwrap();
+
+    // Unknown keys and faults without count are rejected
+    for content in [
+        r#"[[{"address": "0x08000638", "fault_type": "glitch_1", "count": 1, "pass": 2}]]"#,
+        r#"[[{"address": "0x08000638", "fault_type": "glitch_1"}]]"#,
+    ] {
+        std::fs::write(&path, content).unwrap();
+        assert!(attack.load_attacks(2000, &path).is_err());
+    }
+    std::fs::remove_file(&path).unwrap();
+
+    let glitch = get_fault_from("glitch_1").unwrap();
+    let first = attack
+        .fault_at_symbol_offset(2000, "main", 3, &glitch)
+        .unwrap();
+    let second = attack
+        .fault_record_at_pass(2000, 0x08000638, 1, &glitch)
+        .unwrap()
+        .unwrap();
+    assert_eq!(attacks.len(), 1);
+    // Faults are sorted in program order with relative indices
+    assert_eq!(attacks[0][0].index, first.index);
+    assert_eq!(attacks[0][1].index, second.index - first.index);
+    assert_eq!(
+        format!("{:?}", attacks[0][0].fault_type),
+        "Glitch (glitch_2)"
+    );
+}
+
+#[test]
+#[cfg(feature = "serde")]
+/// Test for a checkpointed campaign
+///
+/// This test runs all single glitch attacks on victim_.elf with a checkpoint, resumes
+/// the campaign from a checkpoint in the middle of the attacks and checks that the
+/// same successful attacks are found
+fn resume_campaign_from_checkpoint() {
+    let path = env::temp_dir().join("fault_simulator_checkpoint.json");
+    let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let glitch = get_fault_from("glitch_1").unwrap();
+    let attacks: Vec<Vec<FaultRecord>> = attack
+        .enumerate_fault_candidates(2000, &glitch)
+        .unwrap()
+        .into_iter()
+        .map(|record| vec![record])
+        .collect();
+
+    let expected = attack
+        .run_faults_checkpoint(2000, &attacks, &path, 4)
+        .unwrap();
+    let checkpoint = Checkpoint::read(&path).unwrap();
+    assert_eq!(checkpoint.next, attacks.len());
+    assert_eq!(checkpoint.successful.len(), expected.len());
+    assert!(!expected.is_empty());
+
+    // Interrupted after the first half of the attacks
+    let next = attacks.len() / 2;
+    Checkpoint {
+        total: attacks.len(),
+        next,
+        successful: checkpoint
+            .successful
+            .into_iter()
+            .filter(|index| *index < next)
+            .collect(),
+    }
+    .write(&path)
+    .unwrap();
+    let resumed = attack.resume_faults(2000, &path, &attacks, 4).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let addresses = |results: &[Vec<FaultData>]| -> Vec<u64> {
+        results
+            .iter()
+            .map(|fault_data| fault_data[0].record.address())
+            .collect()
+    };
+    assert_eq!(addresses(&resumed), addresses(&expected));
+}
+
+#[test]
+/// Test for the instruction which wrote the decision
+///
+/// This test runs the failure path of victim_.elf and checks that the writing
+/// instruction of the auth address is located in the code
+fn decision_written_by_code() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let config = SimulationConfig::default();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation
+        .run_with_faults(RunType::Run, false, &[])
+        .unwrap();
+    assert_eq!(simulation.get_trigger(), Some("auth"));
+    let pc = simulation.get_trigger_pc().unwrap();
+    assert!((0x08000000..0x080006c6).contains(&pc), "0x{:X}", pc);
+
+    // No decision within the first instruction
+    simulation.set_instruction_limit(1);
+    simulation
+        .run_with_faults(RunType::Run, false, &[])
+        .unwrap();
+    assert!(simulation.get_trigger_pc().is_none());
+}
+
+#[test]
+/// Test for the state change callback
+///
+/// This test runs the failure path of victim_.elf and checks that the change to the
+/// failed state is reported with the program counter of the code
+fn report_state_changes() {
+    let changes = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let mut config = SimulationConfig::default();
+    let reported = changes.clone();
+    config.set_state_callback(move |state, pc| reported.lock().unwrap().push((state, pc)));
+
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation
+        .run_with_faults(RunType::Run, false, &[])
+        .unwrap();
+    let changes = changes.lock().unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].0, RunState::Failed);
+    assert!((0x08000000..0x080006c6).contains(&changes[0].1));
+}
+
+#[test]
+/// Test for reused simulation instances
+///
+/// This test runs a glitch on victim_.elf and a nominal run afterwards in the same
+/// instance. The code modified by the glitch has to be loaded again for the second run
+fn reuse_instance_after_fault() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let config = SimulationBuilder::new()
+        .count_instructions(true)
+        .build_config()
+        .unwrap();
+    let mut nominal = Control::new(&file_data, &config).unwrap();
+    nominal.run_with_faults(RunType::Run, false, &[]).unwrap();
+
+    let attack = FaultAttacks::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let glitch = get_fault_from("glitch_1").unwrap();
+    let record = attack
+        .fault_at_symbol_offset(2000, "main", 3, &glitch)
+        .unwrap();
+
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    for _ in 0..2 {
+        simulation
+            .run_with_faults(RunType::Run, false, &[record.clone()])
+            .unwrap();
+        simulation
+            .run_with_faults(RunType::Run, false, &[])
+            .unwrap();
+        assert_eq!(simulation.get_state(), RunState::Failed);
+        assert_eq!(
+            simulation.get_instruction_count(),
+            nominal.get_instruction_count()
+        );
+    }
+}
+
+#[test]
+/// Test for the list of mapped memory regions
+///
+/// This test checks that the code of victim_.elf, the auth address and a user defined
+/// MMIO peripheral are part of the mapped regions
+fn list_memory_regions() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let mut config = SimulationConfig::default();
+    config.add_mmio_handler(0x4000_0000, 0x1000, |_, _| 0, |_, _, _| {});
+    let simulation = Control::new(&file_data, &config).unwrap();
+    let regions = simulation.memory_regions().unwrap();
+    let mapped = |address: u64| {
+        regions
+            .iter()
+            .find(|(start, size, _)| address >= *start && address < start + *size as u64)
+            .map(|(_, _, permission)| *permission)
+    };
+
+    assert!(mapped(0x0800_0628).unwrap().contains(Permission::EXEC));
+    assert!(mapped(MemoryLayout::default().auth_base).is_some());
+    assert!(mapped(0x4000_0000).is_some());
+    assert!(mapped(0x3000_0000).is_none());
+}
+
+#[test]
+/// Test for the classification of invalid memory accesses
+///
+/// This test replaces the first instructions of victim_.elf with a load from unmapped
+/// memory and checks the recorded access with and without continuing the execution
+fn classify_invalid_access() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let entry = file_data.entry_point & !1;
+    // "movs r0, #0x30", "lsls r0, r0, #24", "ldr r1, [r0]"
+    let code = [0x30, 0x20, 0x00, 0x06, 0x01, 0x68];
+    let expected = InvalidAccess {
+        mem_type: MemType::READ_UNMAPPED,
+        address: 0x3000_0000,
+        pc: entry + 4,
+    };
+
+    let config = SimulationConfig::default();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation.start();
+    simulation.write_memory(entry, &code).unwrap();
+    let stop_reason = simulation.run_steps(3);
+    assert_eq!(stop_reason, StopReason::MemoryFault(MemType::READ_UNMAPPED));
+    assert_eq!(simulation.get_invalid_accesses(), &[expected]);
+    assert_eq!(simulation.get_state(), RunState::Error);
+
+    let config = SimulationConfig {
+        continue_after_invalid_access: true,
+        ..Default::default()
+    };
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation.start();
+    simulation.write_memory(entry, &code).unwrap();
+    let stop_reason = simulation.run_steps(3);
+    assert_eq!(stop_reason, StopReason::InstructionLimit);
+    assert_eq!(simulation.get_invalid_accesses(), &[expected]);
+    assert_eq!(simulation.read_register(1).unwrap(), 0);
+}
+
+#[test]
+/// Test for repeated runs with invalid accesses
+///
+/// This test runs a load from unmapped memory twice on the same simulation and checks
+/// that the page mapped in the first run is removed before the second run
+fn unmap_pages_of_invalid_access() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let entry = file_data.entry_point & !1;
+    // "movs r0, #0x30", "lsls r0, r0, #24", "ldr r1, [r0]"
+    let code = [0x30, 0x20, 0x00, 0x06, 0x01, 0x68];
+    let config = SimulationConfig {
+        continue_after_invalid_access: true,
+        ..Default::default()
+    };
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    let is_mapped = |simulation: &Control| {
+        simulation
+            .memory_regions()
+            .unwrap()
+            .iter()
+            .any(|(start, size, _)| (*start..*start + *size as u64).contains(&0x3000_0000))
+    };
+
+    for _ in 0..2 {
+        simulation.start();
+        assert!(!is_mapped(&simulation));
+        simulation.write_memory(entry, &code).unwrap();
+        simulation.run_steps(3);
+        assert_eq!(simulation.get_invalid_accesses().len(), 1);
+        assert!(is_mapped(&simulation));
+    }
+}
+
+#[test]
+/// Test for execute-only code pages
+///
+/// This test maps the code of victim_.elf without write permission, replaces the first
+/// instructions with a store into the code and checks that the write is detected
+fn protect_code_pages() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let entry = file_data.entry_point & !1;
+    let config = SimulationBuilder::new()
+        .code_permission(Permission::READ | Permission::EXEC)
+        .build_config()
+        .unwrap();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation.start();
+    // "movs r0, #0x08", "lsls r0, r0, #24", "str r1, [r0]"
+    simulation
+        .write_memory(entry, &[0x08, 0x20, 0x00, 0x06, 0x01, 0x60])
+        .unwrap();
+
+    let stop_reason = simulation.run_steps(3);
+    assert_eq!(stop_reason, StopReason::MemoryFault(MemType::WRITE_PROT));
+    let access = simulation.get_invalid_accesses()[0];
+    assert_eq!((access.address, access.pc), (0x0800_0000, entry + 4));
+}
+
+#[test]
+/// Test for the detection of endless loops
+///
+/// This test replaces the first instruction of victim_.elf with a branch to itself and
+/// checks that the loop is only detected with a configured loop limit
+fn detect_endless_loop() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let entry = file_data.entry_point & !1;
+    // "b ."
+    let code = [0xfe, 0xe7];
+
+    let config = SimulationConfig::default();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation.start();
+    simulation.write_memory(entry, &code).unwrap();
+    assert_eq!(simulation.run_steps(1000), StopReason::InstructionLimit);
+
+    let config = SimulationBuilder::new()
+        .loop_limit(100)
+        .build_config()
+        .unwrap();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation.start();
+    simulation.write_memory(entry, &code).unwrap();
+    assert_eq!(simulation.run_steps(1000), StopReason::LoopDetected(entry));
+    assert_eq!(simulation.get_state(), RunState::Error);
+}
+
+#[test]
+/// Test for the clock cycle counter
+///
+/// This test runs victim_.elf with and without the timing model and checks that
+/// loads, stores and branches add cycles to the executed instructions
+fn count_clock_cycles() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let config = SimulationConfig::default();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation
+        .run_with_faults(RunType::Run, false, &[])
+        .unwrap();
+    assert_eq!(simulation.cycles_executed(), 0);
+
+    let config = SimulationBuilder::new()
+        .count_cycles(true)
+        .count_instructions(true)
+        .build_config()
+        .unwrap();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation
+        .run_with_faults(RunType::Run, false, &[])
+        .unwrap();
+    assert!(simulation.cycles_executed() > simulation.get_instruction_count());
+}
+
+#[test]
+/// Test for the stack usage tracking
+///
+/// This test runs victim_.elf with and without tracking and checks that the recorded
+/// stack usage is within the stack section
+fn track_stack_usage() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let stack = &file_data.section_map[".stack"];
+    let config = SimulationConfig::default();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation
+        .run_with_faults(RunType::Run, false, &[])
+        .unwrap();
+    assert!(simulation.max_stack_usage().is_none());
+
+    let config = SimulationConfig {
+        track_stack_usage: true,
+        ..Default::default()
+    };
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    simulation
+        .run_with_faults(RunType::Run, false, &[])
+        .unwrap();
+    let usage = simulation.max_stack_usage().unwrap();
+    assert!(usage > 0 && usage <= stack.sh_size, "{}", usage);
+    assert_eq!(
+        simulation.min_sp_reached().unwrap(),
+        stack.sh_addr + stack.sh_size - usage
+    );
+}
+
+#[test]
+/// Test for the program check
+///
+/// This test verifies victim_.elf and checks the reported state of a success path
+/// which is stopped before the decision
+fn verify_program_paths() {
+    let file_data = ElfFile::new(std::path::PathBuf::from("tests/bin/victim_.elf")).unwrap();
+    let config = SimulationConfig::default();
+    let mut simulation = Control::new(&file_data, &config).unwrap();
+    assert_eq!(simulation.verify_program(), Ok(()));
+
+    simulation.set_instruction_limit(1);
+    let error = simulation.verify_program().unwrap_err();
+    assert_eq!(error, ProgramCheckError::SuccessPath(RunState::Init));
+    assert!(simulation
+        .check_program()
+        .unwrap_err()
+        .contains("Success path"));
+}